@@ -0,0 +1,394 @@
+// Copyright (C) 2023 Tristan Gerritsen <tristan@thewoosh.org>
+// All Rights Reserved.
+
+use std::{
+    error::Error,
+    fmt::Display,
+    io::{
+        self,
+        Write,
+    },
+    str::FromStr,
+};
+
+use c_lexer::{
+    Encoding,
+    FileId,
+    Keyword,
+    Lexer,
+    PreprocessorToken,
+    PreprocessorTokenKind,
+    Punctuator,
+    SourceMap,
+    Span,
+};
+
+/// How `--dump-tokens` prints the tokens.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum DumpFormat {
+    /// A line per token with its location, kind, spelling and flags.
+    #[default]
+    Text,
+
+    /// An array with an object per token, for other tools to consume.
+    Json,
+
+    /// The format of `clang -dump-tokens`, so the output can be diffed
+    /// against that of clang.
+    Clang,
+}
+
+impl FromStr for DumpFormat {
+    type Err = UnknownDumpFormat;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "text" => Ok(Self::Text),
+            "json" => Ok(Self::Json),
+            "clang" => Ok(Self::Clang),
+            _ => Err(UnknownDumpFormat),
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub struct UnknownDumpFormat;
+
+impl Display for UnknownDumpFormat {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("UnknownDumpFormat")
+    }
+}
+
+impl Error for UnknownDumpFormat {
+}
+
+/// Prints the tokens of a file, which were produced by the lexer.
+pub struct TokenDumper<'a> {
+    pub source_map: &'a SourceMap,
+    pub file: FileId,
+    pub lexer: &'a Lexer<'a>,
+}
+
+impl TokenDumper<'_> {
+    pub fn dump(&self, format: DumpFormat, tokens: &[PreprocessorToken<'_>], out: &mut impl Write) -> io::Result<()> {
+        match format {
+            DumpFormat::Text => self.dump_text(tokens, out),
+            DumpFormat::Json => self.dump_json(tokens, out),
+            DumpFormat::Clang => self.dump_clang(tokens, out),
+        }
+    }
+
+    fn dump_text(&self, tokens: &[PreprocessorToken<'_>], out: &mut impl Write) -> io::Result<()> {
+        for token in tokens {
+            let (location, _) = self.source_map.resolve(token.span);
+            write!(out, "{location} {} {:?}", self.kind(token), self.lexer.cleaned_spelling(token))?;
+
+            if token.flags.at_start_of_line {
+                write!(out, " [at_start_of_line]")?;
+            }
+
+            if token.flags.has_leading_whitespace {
+                write!(out, " [has_leading_whitespace]")?;
+            }
+
+            writeln!(out)?;
+        }
+
+        Ok(())
+    }
+
+    fn dump_json(&self, tokens: &[PreprocessorToken<'_>], out: &mut impl Write) -> io::Result<()> {
+        let tokens: Vec<_> = tokens.iter()
+            .map(|token| {
+                let (start, end) = self.source_map.resolve(token.span);
+                serde_json::json!({
+                    "kind": self.kind(token),
+                    "spelling": self.lexer.cleaned_spelling(token),
+                    "span": {
+                        "start": token.span.start(),
+                        "end": token.span.end(),
+                        "start_location": { "line": start.line, "column": start.column },
+                        "end_location": { "line": end.line, "column": end.column },
+                    },
+                    "flags": {
+                        "at_start_of_line": token.flags.at_start_of_line,
+                        "has_leading_whitespace": token.flags.has_leading_whitespace,
+                    },
+                })
+            })
+            .collect();
+
+        serde_json::to_writer_pretty(&mut *out, &tokens)?;
+        writeln!(out)
+    }
+
+    fn dump_clang(&self, tokens: &[PreprocessorToken<'_>], out: &mut impl Write) -> io::Result<()> {
+        for token in tokens {
+            let spelling = self.lexer.cleaned_spelling(token);
+            write!(out, "{} '{spelling}'\t", self.clang_kind(token))?;
+
+            if token.flags.at_start_of_line {
+                write!(out, " [StartOfLine]")?;
+            }
+
+            if self.clang_leading_space(token) {
+                write!(out, " [LeadingSpace]")?;
+            }
+
+            let raw = self.lexer.spelling(token);
+            if raw != spelling {
+                write!(out, " [UnClean='{raw}']")?;
+            }
+
+            writeln!(out, "\tLoc=<{}>", self.clang_location(token.span))?;
+        }
+
+        let end = self.source_map.file(self.file).text().len();
+        writeln!(out, "eof ''\t\tLoc=<{}>", self.clang_location(Span::new(self.file, end, end)))
+    }
+
+    /// The name of the kind, where identifiers that are keywords are named
+    /// `keyword`.
+    fn kind(&self, token: &PreprocessorToken<'_>) -> &'static str {
+        match token.kind {
+            PreprocessorTokenKind::Identifier(symbol) if self.lexer.options().keyword(symbol).is_some() => "keyword",
+            _ => token.kind.name(),
+        }
+    }
+
+    /// The name clang gives the kind of token, e.g. `l_paren` or
+    /// `numeric_constant`.
+    fn clang_kind(&self, token: &PreprocessorToken<'_>) -> &'static str {
+        let options = self.lexer.options();
+
+        match &token.kind {
+            PreprocessorTokenKind::Identifier(symbol) => {
+                if let Some(punctuator) = options.alternative_token(*symbol) {
+                    return clang_punctuator(punctuator);
+                }
+
+                match options.keyword(*symbol) {
+                    Some(keyword) => clang_keyword(keyword),
+                    None => "identifier",
+                }
+            }
+            PreprocessorTokenKind::PpNumber(..) => "numeric_constant",
+            PreprocessorTokenKind::CharacterConstant(spelling) => match Encoding::split_prefix(spelling).0 {
+                Encoding::None => "char_constant",
+                Encoding::Utf8 => "utf8_char_constant",
+                Encoding::Utf16 => "utf16_char_constant",
+                Encoding::Utf32 => "utf32_char_constant",
+                Encoding::Wide => "wide_char_constant",
+            },
+            PreprocessorTokenKind::StringLiteral(spelling) => match Encoding::split_prefix(spelling).0 {
+                Encoding::None => "string_literal",
+                Encoding::Utf8 => "utf8_string_literal",
+                Encoding::Utf16 => "utf16_string_literal",
+                Encoding::Utf32 => "utf32_string_literal",
+                Encoding::Wide => "wide_string_literal",
+            },
+            PreprocessorTokenKind::Punctuator(punctuator) | PreprocessorTokenKind::Digraph(punctuator) => {
+                clang_punctuator(*punctuator)
+            }
+            PreprocessorTokenKind::HeaderName(..) => "header_name",
+            PreprocessorTokenKind::Comment(..) => "comment",
+            PreprocessorTokenKind::Whitespace(..) | PreprocessorTokenKind::OtherUniversalCharacterName(..)
+                | PreprocessorTokenKind::NonWhiteSpaceCharacter(..) => "unknown",
+        }
+    }
+
+    /// Clang doesn't count the line break before a token at the start of a
+    /// line as leading space, only the whitespace and comments after it.
+    fn clang_leading_space(&self, token: &PreprocessorToken<'_>) -> bool {
+        if !token.flags.at_start_of_line {
+            return token.flags.has_leading_whitespace;
+        }
+
+        let text = self.source_map.file(token.span.file).text();
+        token.flags.has_leading_whitespace && !text[..token.span.start()].ends_with(['\n', '\r'])
+    }
+
+    fn clang_location(&self, span: Span) -> String {
+        let (location, _) = self.source_map.resolve(span);
+        format!("{}:{location}", self.source_map.file(span.file).path().display())
+    }
+}
+
+/// Clang names keywords after their spelling, except for the GNU spellings
+/// that are aliases of another keyword.
+fn clang_keyword(keyword: Keyword) -> &'static str {
+    match keyword {
+        Keyword::GnuAsm | Keyword::GnuInline | Keyword::GnuRestrict => clang_keyword(keyword.canonical()),
+        Keyword::GnuAttribute => "__attribute",
+        keyword => keyword.into(),
+    }
+}
+
+/// The names of `clang/Basic/TokenKinds.def`.
+fn clang_punctuator(punctuator: Punctuator) -> &'static str {
+    match punctuator {
+        Punctuator::LeftSquareBracket => "l_square",
+        Punctuator::RightSquareBracket => "r_square",
+        Punctuator::LeftParenthesis => "l_paren",
+        Punctuator::RightParenthesis => "r_paren",
+        Punctuator::LeftCurlyBracket => "l_brace",
+        Punctuator::RightCurlyBracket => "r_brace",
+        Punctuator::FullStop => "period",
+        Punctuator::PointerMemberAccessOperator => "arrow",
+        Punctuator::PointerToMemberOperator => "periodstar",
+        Punctuator::PointerToMemberPointerOperator => "arrowstar",
+        Punctuator::IncrementOperator => "plusplus",
+        Punctuator::DecrementOperator => "minusminus",
+        Punctuator::ReferenceOperatorOrBitwiseAnd => "amp",
+        Punctuator::Asterisk => "star",
+        Punctuator::PlusSign => "plus",
+        Punctuator::Minus => "minus",
+        Punctuator::Tilde => "tilde",
+        Punctuator::ExclamationMark => "exclaim",
+        Punctuator::Solidus => "slash",
+        Punctuator::Percentage => "percent",
+        Punctuator::LeftBitShift => "lessless",
+        Punctuator::RightBitShift => "greatergreater",
+        Punctuator::LessThan => "less",
+        Punctuator::GreaterThan => "greater",
+        Punctuator::LessThanOrEqualTo => "lessequal",
+        Punctuator::GreaterThanOrEqualTo => "greaterequal",
+        Punctuator::EqualTo => "equalequal",
+        Punctuator::NotEqualTo => "exclaimequal",
+        Punctuator::ThreeWayComparison => "spaceship",
+        Punctuator::BitwiseXor => "caret",
+        Punctuator::BitwiseOr => "pipe",
+        Punctuator::LogicalAnd => "ampamp",
+        Punctuator::LogicalOr => "pipepipe",
+        Punctuator::QuestionMark => "question",
+        Punctuator::Colon => "colon",
+        Punctuator::DoubleColon => "coloncolon",
+        Punctuator::Semicolon => "semi",
+        Punctuator::Ellipsis => "ellipsis",
+        Punctuator::EqualsSign => "equal",
+        Punctuator::MultiplyAssign => "starequal",
+        Punctuator::DivideAssign => "slashequal",
+        Punctuator::ModuloAssign => "percentequal",
+        Punctuator::AddAssign => "plusequal",
+        Punctuator::SubtractAssign => "minusequal",
+        Punctuator::LeftBitShiftAssign => "lesslessequal",
+        Punctuator::RightBitShiftAssign => "greatergreaterequal",
+        Punctuator::BitwiseAndAssign => "ampequal",
+        Punctuator::BitwiseXorAssign => "caretequal",
+        Punctuator::BitwiseOrAssign => "pipeequal",
+        Punctuator::Comma => "comma",
+        Punctuator::Pound => "hash",
+        Punctuator::DoublePound => "hashhash",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rstest::rstest;
+    use super::*;
+    use pretty_assertions::assert_eq;
+    use c_lexer::{
+        Language,
+        LexerOptions,
+    };
+
+    fn dump(format: DumpFormat, text: &str, options: LexerOptions) -> String {
+        let mut source_map = SourceMap::new();
+        let file = source_map.add_file("test.c", text);
+
+        let mut lexer = source_map.lexer(file, options);
+        let tokens: Vec<_> = lexer.by_ref().collect();
+
+        let mut out = Vec::new();
+        TokenDumper { source_map: &source_map, file, lexer: &lexer }
+            .dump(format, &tokens, &mut out)
+            .unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn clang() {
+        let text = "int main() {\n  return L'a' + 0x1\\\n0;\n}\n";
+        assert_eq!(dump(DumpFormat::Clang, text, LexerOptions::default()), [
+            "int 'int'\t [StartOfLine]\tLoc=<test.c:1:1>",
+            "identifier 'main'\t [LeadingSpace]\tLoc=<test.c:1:5>",
+            "l_paren '('\t\tLoc=<test.c:1:9>",
+            "r_paren ')'\t\tLoc=<test.c:1:10>",
+            "l_brace '{'\t [LeadingSpace]\tLoc=<test.c:1:12>",
+            "return 'return'\t [StartOfLine] [LeadingSpace]\tLoc=<test.c:2:3>",
+            "wide_char_constant 'L'a''\t [LeadingSpace]\tLoc=<test.c:2:10>",
+            "plus '+'\t [LeadingSpace]\tLoc=<test.c:2:15>",
+            "numeric_constant '0x10'\t [LeadingSpace] [UnClean='0x1\\\n0']\tLoc=<test.c:2:17>",
+            "semi ';'\t\tLoc=<test.c:3:2>",
+            "r_brace '}'\t [StartOfLine]\tLoc=<test.c:4:1>",
+            "eof ''\t\tLoc=<test.c:5:1>",
+            "",
+        ].join("\n"));
+    }
+
+    #[rstest]
+    #[case("__attribute__ __inline__ __int128", "__attribute", "inline", "__int128")]
+    #[case("a and <=>", "identifier", "ampamp", "spaceship")]
+    fn clang_kinds(#[case] text: &str, #[case] first: &str, #[case] second: &str, #[case] third: &str) {
+        let options = LexerOptions {
+            language: Language::Cpp,
+            dialect: c_lexer::Dialect::GNU,
+            ..Default::default()
+        };
+
+        let kinds: Vec<_> = dump(DumpFormat::Clang, text, options)
+            .lines()
+            .map(|line| line.split(' ').next().unwrap().to_string())
+            .collect();
+        assert_eq!(kinds, [first, second, third, "eof"]);
+    }
+
+    #[test]
+    fn text() {
+        assert_eq!(dump(DumpFormat::Text, "int x;", LexerOptions::default()), [
+            "1:1 keyword \"int\" [at_start_of_line]",
+            "1:5 identifier \"x\" [has_leading_whitespace]",
+            "1:6 punctuator \";\"",
+            "",
+        ].join("\n"));
+    }
+
+    #[test]
+    fn json() {
+        let json: serde_json::Value = serde_json::from_str(&dump(DumpFormat::Json, "x\n  1", LexerOptions::default())).unwrap();
+        assert_eq!(json, serde_json::json!([
+            {
+                "kind": "identifier",
+                "spelling": "x",
+                "span": {
+                    "start": 0,
+                    "end": 1,
+                    "start_location": { "line": 1, "column": 1 },
+                    "end_location": { "line": 1, "column": 2 },
+                },
+                "flags": { "at_start_of_line": true, "has_leading_whitespace": false },
+            },
+            {
+                "kind": "pp_number",
+                "spelling": "1",
+                "span": {
+                    "start": 4,
+                    "end": 5,
+                    "start_location": { "line": 2, "column": 3 },
+                    "end_location": { "line": 2, "column": 4 },
+                },
+                "flags": { "at_start_of_line": true, "has_leading_whitespace": true },
+            },
+        ]));
+    }
+
+    #[rstest]
+    #[case("text", Ok(DumpFormat::Text))]
+    #[case("json", Ok(DumpFormat::Json))]
+    #[case("clang", Ok(DumpFormat::Clang))]
+    #[case("yaml", Err(UnknownDumpFormat))]
+    fn from_str(#[case] input: &str, #[case] expected: Result<DumpFormat, UnknownDumpFormat>) {
+        assert_eq!(input.parse(), expected);
+    }
+}
@@ -1,16 +1,196 @@
 // Copyright (C) 2023 Tristan Gerritsen <tristan@thewoosh.org>
 // All Rights Reserved.
 
+mod dump;
+
+use std::io::Write;
+
 use c_lexer::{
-    Lexer,
-    Token,
+    Diagnostic,
+    InputCharset,
+    LexerOptions,
+    SecurityChecks,
+    Severity,
+    SourceMap,
 };
 
+use self::dump::{
+    DumpFormat,
+    TokenDumper,
+};
+
+struct Options {
+    lexer: LexerOptions,
+    input_charset: InputCharset,
+    dump_format: DumpFormat,
+    path: String,
+}
+
 fn main() {
-    let data = std::fs::read_to_string("test.c").unwrap();
+    let Options { lexer: options, input_charset, dump_format, path } = parse_options();
+    let mut source_map = SourceMap::new();
+    let file = match source_map.load(&path, input_charset) {
+        Ok(file) => file,
+        Err(error) => {
+            eprintln!("error: {path}: {error}");
+            std::process::exit(1);
+        }
+    };
+
+    let mut lexer = source_map.lexer(file, options.clone());
+    let tokens: Vec<_> = lexer.by_ref().collect();
+
+    let mut diagnostics = lexer.take_diagnostics();
+    diagnostics.extend(tokens.iter().filter_map(|token| {
+        token.clone().into_token(&options).err()?.to_diagnostic(token.span)
+    }));
+    diagnostics.sort_by_key(|diagnostic| diagnostic.span.start());
+
+    let mut out = std::io::stdout().lock();
+    let dumper = TokenDumper { source_map: &source_map, file, lexer: &lexer };
+    match dumper.dump(dump_format, &tokens, &mut out).and_then(|()| out.flush()) {
+        // The output was piped into e.g. `head`, which is fine.
+        Err(error) if error.kind() == std::io::ErrorKind::BrokenPipe => (),
+        Err(error) => {
+            eprintln!("error: failed to write tokens: {error}");
+            std::process::exit(1);
+        }
+        Ok(()) => (),
+    }
+
+    for diagnostic in &diagnostics {
+        print_diagnostic(&source_map, diagnostic);
+    }
+
+    if diagnostics.iter().any(|diagnostic| diagnostic.severity() == Severity::Error) {
+        std::process::exit(1);
+    }
+}
+
+fn print_diagnostic(source_map: &SourceMap, diagnostic: &Diagnostic) {
+    let file = source_map.file(diagnostic.span.file);
+    let (location, _) = source_map.resolve(diagnostic.span);
+    eprintln!("{}:{location}: {diagnostic}", file.path().display());
+}
+
+fn parse_options() -> Options {
+    let mut options = LexerOptions::default();
+    let mut input_charset = InputCharset::default();
+    let mut dump_format = DumpFormat::default();
+    let mut path = None;
+
+    let mut arguments = std::env::args().skip(1);
+    while let Some(argument) = arguments.next() {
+        if let Some(standard) = argument.strip_prefix("-std=") {
+            // `gnu17` is `c17` with the GNU extensions.
+            let (iso_standard, gnu) = match standard.strip_prefix("gnu") {
+                Some(version) => (format!("c{version}"), true),
+                None => (standard.to_string(), false),
+            };
+
+            let Ok(iso_standard) = iso_standard.parse() else {
+                eprintln!("error: invalid value '{standard}' in '{argument}'");
+                std::process::exit(1);
+            };
+
+            options.standard = iso_standard;
+            options.dialect.gnu = gnu;
+            continue;
+        }
+
+        if argument == "-fms-extensions" {
+            options.dialect.msvc = true;
+            continue;
+        }
+
+        if argument == "-Wsecurity" {
+            options.security = SecurityChecks::ALL;
+            continue;
+        }
+
+        if argument == "-Wno-bidirectional-control-character" {
+            options.security.bidirectional_control_characters = false;
+            continue;
+        }
+
+        if argument == "-Wno-confusable-identifier" {
+            options.security.confusable_identifiers = false;
+            continue;
+        }
+
+        if argument == "-pedantic" || argument == "-Wpedantic" {
+            options.pedantic = true;
+            continue;
+        }
+
+        if let Some(charset) = argument.strip_prefix("-finput-charset=") {
+            let Ok(charset) = charset.parse() else {
+                eprintln!("error: invalid value '{charset}' in '{argument}'");
+                std::process::exit(1);
+            };
+
+            input_charset = charset;
+            continue;
+        }
+
+        if let Some(language) = argument.strip_prefix("-x") {
+            let language = match language {
+                "" => arguments.next().unwrap_or_else(|| {
+                    eprintln!("error: argument to '-x' is missing (expected 1 value)");
+                    std::process::exit(1);
+                }),
+                language => language.to_string(),
+            };
+
+            let Ok(language) = language.parse() else {
+                eprintln!("error: language not recognized: '{language}'");
+                std::process::exit(1);
+            };
+
+            options.language = language;
+            continue;
+        }
+
+        if argument == "-trigraphs" {
+            options.trigraphs = true;
+            continue;
+        }
+
+        if argument == "--dump-tokens" {
+            dump_format = DumpFormat::Text;
+            continue;
+        }
+
+        if let Some(format) = argument.strip_prefix("--dump-tokens=") {
+            let Ok(format) = format.parse() else {
+                eprintln!("error: invalid value '{format}' in '{argument}', expected text, json or clang");
+                std::process::exit(1);
+            };
+
+            dump_format = format;
+            continue;
+        }
+
+        if argument.starts_with('-') {
+            eprintln!("error: unknown argument: '{argument}'");
+            std::process::exit(1);
+        }
+
+        if path.replace(argument).is_some() {
+            eprintln!("error: only one input file is supported");
+            std::process::exit(1);
+        }
+    }
+
+    let Some(path) = path else {
+        eprintln!("error: no input files");
+        std::process::exit(1);
+    };
 
-    let tokens: Vec<Token> = Lexer::new(&data)
-        .filter_map(|x| x.try_into().ok())
-        .collect();
-    println!("Tokens: {tokens:#?}");
+    Options {
+        lexer: options,
+        input_charset,
+        dump_format,
+        path,
+    }
 }
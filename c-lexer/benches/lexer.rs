@@ -0,0 +1,64 @@
+// Copyright (C) 2023 Tristan Gerritsen <tristan@thewoosh.org>
+// All Rights Reserved.
+
+use std::fmt::Write;
+
+use c_lexer::Lexer;
+use criterion::{
+    criterion_group,
+    criterion_main,
+    Criterion,
+    Throughput,
+};
+
+/// Generates a translation unit that resembles preprocessed C code, with a
+/// mix of declarations, expressions, literals and comments.
+fn generate_source(functions: usize) -> String {
+    let mut source = String::from("#include <stdio.h>\n\n");
+
+    for index in 0..functions {
+        _ = write!(source, "\
+/*
+ * Computes something interesting for iteration {index}.
+ */
+static unsigned long long function_{index}(const char *restrict name, int count) {{
+    // Accumulate the values in a local variable.
+    unsigned long long accumulator = 0x{index:X}ull;
+    for (int i = 0; i < count; ++i) {{
+        accumulator += (unsigned long long)name[i % 16] * 31u + 1.5e-3;
+        if (accumulator >= 1'000'000 && name[0] != '\\0') {{
+            printf(\"%s: %llu\\n\", name, accumulator);
+        }}
+    }}
+    return accumulator >> 2;
+}}
+
+");
+    }
+
+    source
+}
+
+fn lex(criterion: &mut Criterion) {
+    let source = generate_source(10_000);
+
+    let mut group = criterion.benchmark_group("lexer");
+    group.throughput(Throughput::Bytes(source.len() as u64));
+
+    group.bench_function("preprocessor tokens", |bencher| {
+        bencher.iter(|| Lexer::new(&source).count())
+    });
+
+    group.bench_function("tokens", |bencher| {
+        bencher.iter(|| {
+            Lexer::new(&source)
+                .filter_map(|token| token.into_token(&Default::default()).ok())
+                .count()
+        })
+    });
+
+    group.finish();
+}
+
+criterion_group!(benches, lex);
+criterion_main!(benches);
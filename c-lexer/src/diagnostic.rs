@@ -0,0 +1,192 @@
+// Copyright (C) 2023 Tristan Gerritsen <tristan@thewoosh.org>
+// All Rights Reserved.
+
+use std::fmt::Display;
+
+use crate::{
+    ConstantError,
+    InputCharset,
+    Keyword,
+    LiteralError,
+    Span,
+    Symbol,
+};
+
+/// A problem found in the source code whilst lexing it. The lexer always
+/// recovers from these, so they are collected by the [`Lexer`][crate::Lexer]
+/// instead of aborting the token stream.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Diagnostic {
+    pub kind: DiagnosticKind,
+    pub span: Span,
+}
+
+impl Diagnostic {
+    pub const fn severity(&self) -> Severity {
+        self.kind.severity()
+    }
+
+    /// A stable, machine-readable name for the kind of diagnostic, e.g.
+    /// `unterminated-block-comment`.
+    pub fn code(&self) -> &'static str {
+        self.kind.into()
+    }
+
+    /// The human-readable description of the problem.
+    pub fn message(&self) -> String {
+        self.kind.to_string()
+    }
+}
+
+impl Display for Diagnostic {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: {} [{}]", self.severity(), self.kind, self.code())
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Severity {
+    Warning,
+    Error,
+}
+
+impl Display for Severity {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::Warning => "warning",
+            Self::Error => "error",
+        })
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[derive(strum::IntoStaticStr)]
+#[strum(serialize_all = "kebab-case")]
+pub enum DiagnosticKind {
+    /// Bytes that aren't valid in the encoding of the file, which were
+    /// replaced by U+FFFD. The offset is that of the bytes in the file.
+    InvalidEncoding {
+        charset: InputCharset,
+        offset: u32,
+    },
+
+    /// A `/*` without a matching `*/` before the end of the file.
+    UnterminatedBlockComment,
+
+    /// A `'` without a matching `'` on the same line.
+    UnterminatedCharacterConstant,
+
+    /// A `"` without a matching `"` on the same line.
+    UnterminatedStringLiteral,
+
+    /// A trigraph sequence that wasn't replaced by the given character,
+    /// because trigraphs aren't enabled.
+    TrigraphIgnored(char),
+
+    /// A `\u` or `\U` universal character name that designates a surrogate,
+    /// a value outside the Unicode range, or a basic character (ISO/IEC 9899
+    /// 6.4.3p2).
+    InvalidUniversalCharacterName,
+
+    /// An identifier that isn't in Normalization Form C, as required by
+    /// ISO/IEC 9899 Annex D. The token contains the normalized form.
+    IdentifierNotNormalized,
+
+    /// A character that is a valid preprocessing token on its own, but can't
+    /// be converted to a [`Token`][crate::Token], e.g. `@`.
+    StrayCharacter(char),
+
+    /// A `$` in an identifier, which is an extension that is only diagnosed
+    /// in pedantic mode.
+    DollarInIdentifier,
+
+    /// A keyword of a compiler extension, which is only diagnosed in
+    /// pedantic mode.
+    ExtensionKeyword(Keyword),
+
+    /// A Unicode bidirectional control character in a comment or literal,
+    /// which might make the code display differently than it is compiled.
+    BidirectionalControlCharacter(char),
+
+    /// An identifier that looks like another identifier earlier in the file,
+    /// e.g. a Cyrillic `а` instead of a Latin `a`.
+    ConfusableIdentifier {
+        identifier: Symbol,
+        other: Symbol,
+    },
+
+    /// A pp-number that isn't a valid integer or floating constant.
+    InvalidConstant(ConstantError),
+
+    /// A character constant or string literal that can't be decoded.
+    InvalidLiteral(LiteralError),
+}
+
+impl DiagnosticKind {
+    pub const fn severity(&self) -> Severity {
+        match self {
+            Self::InvalidEncoding { .. } | Self::TrigraphIgnored(..) | Self::IdentifierNotNormalized
+                | Self::DollarInIdentifier | Self::ExtensionKeyword(..) | Self::BidirectionalControlCharacter(..)
+                | Self::ConfusableIdentifier { .. } => Severity::Warning,
+            _ => Severity::Error,
+        }
+    }
+}
+
+impl Display for DiagnosticKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::InvalidEncoding { charset, offset } => write!(f, "invalid {charset} sequence at byte {offset}"),
+            Self::UnterminatedBlockComment => f.write_str("unterminated /* comment"),
+            Self::UnterminatedCharacterConstant => f.write_str("missing terminating ' character"),
+            Self::UnterminatedStringLiteral => f.write_str("missing terminating \" character"),
+            Self::TrigraphIgnored(char) => write!(f, "trigraph converted to '{char}' character ignored, use -trigraphs to enable"),
+            Self::InvalidUniversalCharacterName => f.write_str("invalid universal character name"),
+            Self::IdentifierNotNormalized => f.write_str("identifier is not in Normalization Form C"),
+            Self::StrayCharacter(char) => write!(f, "stray '{char}' (U+{:04X}) in program", *char as u32),
+            Self::DollarInIdentifier => f.write_str("'$' in identifier"),
+            Self::ExtensionKeyword(keyword) => write!(f, "extension keyword '{keyword}' used"),
+            Self::BidirectionalControlCharacter(char) => {
+                write!(f, "bidirectional control character U+{:04X} may change how the code is displayed", *char as u32)
+            }
+            Self::ConfusableIdentifier { identifier, other } => {
+                write!(f, "identifier '{identifier}' is confusable with '{other}'")
+            }
+            Self::InvalidConstant(error) => Display::fmt(error, f),
+            Self::InvalidLiteral(error) => Display::fmt(error, f),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rstest::rstest;
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[rstest]
+    #[case(DiagnosticKind::UnterminatedBlockComment, "unterminated-block-comment", Severity::Error)]
+    #[case(DiagnosticKind::TrigraphIgnored('#'), "trigraph-ignored", Severity::Warning)]
+    #[case(DiagnosticKind::StrayCharacter('@'), "stray-character", Severity::Error)]
+    #[case(DiagnosticKind::InvalidConstant(ConstantError::Overflow), "invalid-constant", Severity::Error)]
+    #[case(DiagnosticKind::InvalidEncoding { charset: InputCharset::Utf8, offset: 3 }, "invalid-encoding", Severity::Warning)]
+    #[case(DiagnosticKind::DollarInIdentifier, "dollar-in-identifier", Severity::Warning)]
+    #[case(DiagnosticKind::ExtensionKeyword(Keyword::GnuAttribute), "extension-keyword", Severity::Warning)]
+    #[case(DiagnosticKind::BidirectionalControlCharacter('\u{202E}'), "bidirectional-control-character", Severity::Warning)]
+    #[case(DiagnosticKind::ConfusableIdentifier { identifier: Symbol::intern("а"), other: Symbol::intern("a") }, "confusable-identifier", Severity::Warning)]
+    fn code_and_severity(#[case] kind: DiagnosticKind, #[case] code: &str, #[case] severity: Severity) {
+        let diagnostic = Diagnostic { kind, span: Span::detached(0, 1) };
+        assert_eq!(diagnostic.code(), code);
+        assert_eq!(diagnostic.severity(), severity);
+    }
+
+    #[test]
+    fn display() {
+        let diagnostic = Diagnostic {
+            kind: DiagnosticKind::StrayCharacter('@'),
+            span: Span::detached(0, 1),
+        };
+
+        assert_eq!(diagnostic.to_string(), "error: stray '@' (U+0040) in program [stray-character]");
+    }
+}
@@ -0,0 +1,417 @@
+// Copyright (C) 2023 Tristan Gerritsen <tristan@thewoosh.org>
+// All Rights Reserved.
+
+use std::{
+    borrow::Cow,
+    error::Error,
+    fmt::Display,
+    str::FromStr,
+};
+
+use crate::{
+    Diagnostic,
+    DiagnosticKind,
+    Span,
+};
+
+/// The encoding of source files without a byte order mark, as selected by
+/// `-finput-charset=`.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub enum InputCharset {
+    #[default]
+    Utf8,
+
+    /// UTF-16 in little-endian byte order.
+    Utf16Le,
+
+    /// UTF-16 in big-endian byte order.
+    Utf16Be,
+
+    /// ISO/IEC 8859-1, of which every byte is the code point with that value.
+    Latin1,
+
+    /// The superset of Latin-1 that is commonly used on Windows, with
+    /// printable characters in the range `0x80..=0x9F`.
+    Windows1252,
+}
+
+impl Display for InputCharset {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::Utf8 => "UTF-8",
+            Self::Utf16Le => "UTF-16LE",
+            Self::Utf16Be => "UTF-16BE",
+            Self::Latin1 => "ISO-8859-1",
+            Self::Windows1252 => "windows-1252",
+        })
+    }
+}
+
+impl FromStr for InputCharset {
+    type Err = UnknownInputCharset;
+
+    /// Parses the value of `-finput-charset=`, e.g. `latin1`. Like iconv,
+    /// plain `utf-16` is big-endian unless the file has a byte order mark.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "utf-8" | "utf8" => Ok(Self::Utf8),
+            "utf-16le" | "utf16le" => Ok(Self::Utf16Le),
+            "utf-16be" | "utf16be" | "utf-16" | "utf16" => Ok(Self::Utf16Be),
+            "iso-8859-1" | "iso8859-1" | "latin1" | "l1" => Ok(Self::Latin1),
+            "windows-1252" | "cp1252" => Ok(Self::Windows1252),
+            _ => Err(UnknownInputCharset),
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub struct UnknownInputCharset;
+
+impl Display for UnknownInputCharset {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("UnknownInputCharset")
+    }
+}
+
+impl Error for UnknownInputCharset {
+}
+
+/// The bytes of a source file decoded to UTF-8 text for the
+/// [`Lexer`][crate::Lexer], see [`Lexer::from_decoded`][crate::Lexer::from_decoded].
+///
+/// A byte order mark is stripped, and selects UTF-8 or UTF-16 regardless of
+/// the [`InputCharset`]. Sequences that are invalid in the encoding are
+/// replaced by U+FFFD and reported as diagnostics. CRLF and CR line endings
+/// are normalized to LF.
+///
+/// The text is only copied when it differs from the bytes, i.e. for UTF-8
+/// files with CR line endings or invalid sequences, and for other encodings.
+/// Offsets in the text can be mapped back to the original bytes with
+/// [`DecodedSource::original_offset`].
+#[derive(Clone, Debug)]
+pub struct DecodedSource<'bytes> {
+    text: Cow<'bytes, str>,
+    offsets: OffsetMap,
+    diagnostics: Vec<Diagnostic>,
+}
+
+impl<'bytes> DecodedSource<'bytes> {
+    pub fn decode(bytes: &'bytes [u8], charset: InputCharset) -> Self {
+        let (charset, bom_length) = match bytes {
+            [0xEF, 0xBB, 0xBF, ..] => (InputCharset::Utf8, 3),
+            [0xFF, 0xFE, ..] => (InputCharset::Utf16Le, 2),
+            [0xFE, 0xFF, ..] => (InputCharset::Utf16Be, 2),
+            _ => (charset, 0),
+        };
+
+        let bytes = &bytes[bom_length..];
+        if charset == InputCharset::Utf8 && !bytes.contains(&b'\r') {
+            if let Ok(text) = std::str::from_utf8(bytes) {
+                return Self {
+                    text: Cow::Borrowed(text),
+                    offsets: OffsetMap::shifted(bom_length),
+                    diagnostics: Vec::new(),
+                };
+            }
+        }
+
+        let mut decoder = Decoder::new(charset, bom_length, bytes.len());
+        match charset {
+            InputCharset::Utf8 => decoder.decode_utf8(bytes),
+            InputCharset::Utf16Le => decoder.decode_utf16(bytes, u16::from_le_bytes),
+            InputCharset::Utf16Be => decoder.decode_utf16(bytes, u16::from_be_bytes),
+            InputCharset::Latin1 => decoder.decode_single_byte(bytes, |byte| byte as char),
+            InputCharset::Windows1252 => decoder.decode_single_byte(bytes, windows_1252_char),
+        }
+
+        Self {
+            text: Cow::Owned(decoder.text),
+            offsets: decoder.offsets,
+            diagnostics: decoder.diagnostics,
+        }
+    }
+
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    pub fn into_text(self) -> Cow<'bytes, str> {
+        self.text
+    }
+
+    /// The problems found whilst decoding, of which the spans are in the
+    /// decoded text.
+    pub fn diagnostics(&self) -> &[Diagnostic] {
+        &self.diagnostics
+    }
+
+    pub(crate) fn into_parts(self) -> (Cow<'bytes, str>, OffsetMap, Vec<Diagnostic>) {
+        (self.text, self.offsets, self.diagnostics)
+    }
+
+    /// The byte offset in the original bytes of the offset in the text.
+    pub fn original_offset(&self, offset: usize) -> usize {
+        self.offsets.original_offset(offset)
+    }
+
+    /// The span in the original bytes of the span in the text.
+    pub fn original_span(&self, span: Span) -> Span {
+        self.offsets.original_span(span)
+    }
+}
+
+/// Maps offsets in decoded text to the bytes it was decoded from, as a list
+/// of checkpoints where the difference between the two changes.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) struct OffsetMap {
+    /// The pairs of offsets in the text and the bytes, sorted by both.
+    checkpoints: Vec<(u32, u32)>,
+}
+
+impl OffsetMap {
+    pub fn shifted(offset: usize) -> Self {
+        Self {
+            checkpoints: vec![(0, offset as u32)],
+        }
+    }
+
+    pub fn original_offset(&self, offset: usize) -> usize {
+        let index = self.checkpoints.partition_point(|(text, _)| *text as usize <= offset) - 1;
+        let (text, original) = self.checkpoints[index];
+        original as usize + (offset - text as usize)
+    }
+
+    pub fn original_span(&self, span: Span) -> Span {
+        Span::new(span.file, self.original_offset(span.start()), self.original_offset(span.end()))
+    }
+
+    fn push(&mut self, text: usize, original: usize) {
+        let (last_text, last_original) = *self.checkpoints.last().unwrap();
+        if original as i64 - text as i64 == last_original as i64 - last_text as i64 {
+            return;
+        }
+
+        let text = text.try_into().expect("file larger than 4 GiB");
+        let original = original.try_into().expect("file larger than 4 GiB");
+        if last_text == text {
+            *self.checkpoints.last_mut().unwrap() = (text, original);
+        } else {
+            self.checkpoints.push((text, original));
+        }
+    }
+}
+
+impl Default for OffsetMap {
+    fn default() -> Self {
+        Self::shifted(0)
+    }
+}
+
+/// Decodes bytes into text with normalized line endings, which can be fed
+/// all at once or in pieces that end on character boundaries.
+pub(crate) struct Decoder {
+    charset: InputCharset,
+    text: String,
+    offsets: OffsetMap,
+    diagnostics: Vec<Diagnostic>,
+
+    /// The offset in the decoded text of the start of `text`, which is not
+    /// zero once the text was drained.
+    text_start: usize,
+
+    /// The offset in the original bytes of the end of `text`.
+    original: usize,
+
+    /// Whether the last character was a CR, of which the LF after it is
+    /// dropped.
+    after_carriage_return: bool,
+}
+
+impl Decoder {
+    pub(crate) fn new(charset: InputCharset, bom_length: usize, capacity: usize) -> Self {
+        Self {
+            charset,
+            text: String::with_capacity(capacity),
+            offsets: OffsetMap::shifted(bom_length),
+            diagnostics: Vec::new(),
+            text_start: 0,
+            original: bom_length,
+            after_carriage_return: false,
+        }
+    }
+
+    /// Moves the text decoded so far to the end of `text`.
+    pub(crate) fn drain_text_into(&mut self, text: &mut String) {
+        self.text_start += self.text.len();
+        text.push_str(&self.text);
+        self.text.clear();
+    }
+
+    pub(crate) fn take_diagnostics(&mut self) -> Vec<Diagnostic> {
+        std::mem::take(&mut self.diagnostics)
+    }
+
+    pub(crate) fn offsets(&self) -> &OffsetMap {
+        &self.offsets
+    }
+
+    pub(crate) fn decode_utf8(&mut self, bytes: &[u8]) {
+        for chunk in bytes.utf8_chunks() {
+            for char in chunk.valid().chars() {
+                self.push(char, char.len_utf8());
+            }
+
+            if !chunk.invalid().is_empty() {
+                self.push_invalid(chunk.invalid().len());
+            }
+        }
+    }
+
+    fn decode_utf16(&mut self, bytes: &[u8], unit: fn([u8; 2]) -> u16) {
+        let chunks = bytes.chunks_exact(2);
+        let remainder = chunks.remainder().len();
+
+        let units = chunks.map(|chunk| unit([chunk[0], chunk[1]]));
+        for char in char::decode_utf16(units) {
+            match char {
+                Ok(char) => self.push(char, char.len_utf16() * 2),
+                Err(..) => self.push_invalid(2),
+            }
+        }
+
+        if remainder != 0 {
+            self.push_invalid(remainder);
+        }
+    }
+
+    fn decode_single_byte(&mut self, bytes: &[u8], decode: fn(u8) -> char) {
+        for byte in bytes {
+            self.push(decode(*byte), 1);
+        }
+    }
+
+    /// Appends the character that spans `length` of the original bytes.
+    fn push(&mut self, char: char, length: usize) {
+        let after_carriage_return = std::mem::replace(&mut self.after_carriage_return, char == '\r');
+        self.original += length;
+
+        match char {
+            '\n' if after_carriage_return => (),
+            '\r' => self.text.push('\n'),
+            _ => self.text.push(char),
+        }
+
+        self.offsets.push(self.text_start + self.text.len(), self.original);
+    }
+
+    fn push_invalid(&mut self, length: usize) {
+        let start = self.text_start + self.text.len();
+        self.diagnostics.push(Diagnostic {
+            kind: DiagnosticKind::InvalidEncoding {
+                charset: self.charset,
+                offset: self.original as u32,
+            },
+            span: Span::detached(start, start + char::REPLACEMENT_CHARACTER.len_utf8()),
+        });
+
+        self.push(char::REPLACEMENT_CHARACTER, length);
+    }
+}
+
+/// The characters of the bytes `0x80..=0x9F` in windows-1252, where the
+/// unassigned ones map to the C1 control characters as specified by WHATWG.
+const WINDOWS_1252_HIGH_CONTROL: [char; 32] = [
+    '\u{20AC}', '\u{81}', '\u{201A}', '\u{192}', '\u{201E}', '\u{2026}', '\u{2020}', '\u{2021}',
+    '\u{2C6}', '\u{2030}', '\u{160}', '\u{2039}', '\u{152}', '\u{8D}', '\u{17D}', '\u{8F}',
+    '\u{90}', '\u{2018}', '\u{2019}', '\u{201C}', '\u{201D}', '\u{2022}', '\u{2013}', '\u{2014}',
+    '\u{2DC}', '\u{2122}', '\u{161}', '\u{203A}', '\u{153}', '\u{9D}', '\u{17E}', '\u{178}',
+];
+
+fn windows_1252_char(byte: u8) -> char {
+    match byte {
+        0x80..=0x9F => WINDOWS_1252_HIGH_CONTROL[byte as usize - 0x80],
+        _ => byte as char,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::ops::Range;
+
+    use rstest::rstest;
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[rstest]
+    #[case(b"int a;", InputCharset::Utf8, "int a;")]
+    #[case(b"\xEF\xBB\xBFint a;", InputCharset::Utf8, "int a;")]
+    #[case(b"\xEF\xBB\xBFint a;", InputCharset::Latin1, "int a;")]
+    #[case(b"\xFF\xFEa\0=\0\xE9\0", InputCharset::Utf8, "a=é")]
+    #[case(b"\xFE\xFF\0a\0=\xD8\x3D\xDE\x00", InputCharset::Utf8, "a=😀")]
+    #[case(b"a\0\n\0", InputCharset::Utf16Le, "a\n")]
+    #[case(b"\0a\0\n", InputCharset::Utf16Be, "a\n")]
+    #[case(b"/* caf\xE9 */", InputCharset::Latin1, "/* café */")]
+    #[case(b"/* \x80\x81\x9F */", InputCharset::Windows1252, "/* €\u{81}Ÿ */")]
+    #[case(b"a\r\nb\rc\n\rd", InputCharset::Utf8, "a\nb\nc\n\nd")]
+    #[case(b"a\0\r\0\n\0b\0", InputCharset::Utf16Le, "a\nb")]
+    fn decode(#[case] bytes: &[u8], #[case] charset: InputCharset, #[case] expected: &str) {
+        let source = DecodedSource::decode(bytes, charset);
+        assert_eq!(source.text(), expected);
+        assert_eq!(source.diagnostics(), &[]);
+    }
+
+    #[test]
+    fn borrows_when_unchanged() {
+        let source = DecodedSource::decode(b"\xEF\xBB\xBFint a;\n", InputCharset::Utf8);
+        assert!(matches!(source.text, Cow::Borrowed("int a;\n")));
+        assert_eq!(source.original_offset(4), 7);
+    }
+
+    #[rstest]
+    #[case(b"a\xFFb", InputCharset::Utf8, "a\u{FFFD}b", &[(1, 1..4)])]
+    #[case(b"\xC3(\xE2\x82", InputCharset::Utf8, "\u{FFFD}(\u{FFFD}", &[(0, 0..3), (2, 4..7)])]
+    #[case(b"a\0\x00\xD8b\0", InputCharset::Utf16Le, "a\u{FFFD}b", &[(2, 1..4)])]
+    #[case(b"\0a\0", InputCharset::Utf16Be, "a\u{FFFD}", &[(2, 1..4)])]
+    fn invalid_sequences(
+        #[case] bytes: &[u8],
+        #[case] charset: InputCharset,
+        #[case] expected: &str,
+        #[case] invalid: &[(u32, Range<usize>)],
+    ) {
+        let source = DecodedSource::decode(bytes, charset);
+        assert_eq!(source.text(), expected);
+
+        let expected: Vec<_> = invalid.iter()
+            .map(|(offset, range)| Diagnostic {
+                kind: DiagnosticKind::InvalidEncoding { charset, offset: *offset },
+                span: Span::detached(range.start, range.end),
+            })
+            .collect();
+        assert_eq!(source.diagnostics(), expected);
+    }
+
+    #[rstest]
+    #[case(b"a\r\nb", &[(0, 0), (1, 1), (2, 3), (3, 4)])]
+    #[case(b"\xEF\xBB\xBFa\rb", &[(0, 3), (2, 5), (3, 6)])]
+    #[case(b"caf\xE9!", &[(3, 3), (5, 4), (6, 5)])]
+    #[case(b"\xFF\xFEa\0\n\0", &[(0, 2), (1, 4), (2, 6)])]
+    fn original_offsets(#[case] bytes: &[u8], #[case] expected: &[(usize, usize)]) {
+        let source = DecodedSource::decode(bytes, InputCharset::Latin1);
+        for (offset, original) in expected {
+            assert_eq!(source.original_offset(*offset), *original, "offset {offset} of {:?}", source.text());
+        }
+    }
+
+    #[rstest]
+    #[case("utf-8", Ok(InputCharset::Utf8))]
+    #[case("UTF-8", Ok(InputCharset::Utf8))]
+    #[case("utf-16", Ok(InputCharset::Utf16Be))]
+    #[case("UTF-16LE", Ok(InputCharset::Utf16Le))]
+    #[case("latin1", Ok(InputCharset::Latin1))]
+    #[case("ISO-8859-1", Ok(InputCharset::Latin1))]
+    #[case("cp1252", Ok(InputCharset::Windows1252))]
+    #[case("ebcdic", Err(UnknownInputCharset))]
+    fn input_charset_from_str(#[case] input: &str, #[case] expected: Result<InputCharset, UnknownInputCharset>) {
+        assert_eq!(input.parse(), expected);
+    }
+}
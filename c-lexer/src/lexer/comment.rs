@@ -0,0 +1,171 @@
+// Copyright (C) 2023 Tristan Gerritsen <tristan@thewoosh.org>
+// All Rights Reserved.
+
+use crate::{
+    Diagnostic,
+    DiagnosticKind,
+    PreprocessorToken,
+    PreprocessorTokenKind,
+    Punctuator,
+};
+
+use super::{
+    source_code::ByteSet,
+    Lexer,
+};
+
+const LINE_END: ByteSet = ByteSet::new(b"\n\r");
+const ASTERISK: ByteSet = ByteSet::new(b"*");
+
+impl<'source_code> Lexer<'source_code> {
+    /// Consumes a `/`, which either starts a comment (ISO/IEC 9899 6.4.9) or
+    /// is one of the `/` and `/=` punctuators.
+    pub(super) fn consume_comment_or_solidus(&mut self) -> PreprocessorToken<'source_code> {
+        let reset_position = self.source_code.position();
+
+        _ = self.source_code.next();
+
+        match self.source_code.peek() {
+            Some('/') => self.consume_line_comment(reset_position.index()),
+            Some('*') => self.consume_block_comment(reset_position.index()),
+            _ => {
+                self.source_code.restore(reset_position);
+                self.consume_two_possible_punctuators(
+                    '=',
+                    Punctuator::Solidus,
+                    Punctuator::DivideAssign,
+                )
+            }
+        }
+    }
+
+    /// Consumes the second `/` of the `//` and everything up until the end of
+    /// the line, the newline itself excluded.
+    fn consume_line_comment(&mut self, start: usize) -> PreprocessorToken<'source_code> {
+        loop {
+            self.source_code.skip_until(&LINE_END);
+
+            match self.source_code.peek() {
+                Some('\n' | '\r') | None => break,
+                Some(..) => _ = self.source_code.next(),
+            }
+        }
+
+        let end = self.source_code.index();
+        self.token(PreprocessorTokenKind::Comment(self.cleaned(start, end)), start, end)
+    }
+
+    /// Consumes the `*` of the `/*` up until and including the `*/`.
+    fn consume_block_comment(&mut self, start: usize) -> PreprocessorToken<'source_code> {
+        _ = self.source_code.next();
+
+        loop {
+            self.source_code.skip_until(&ASTERISK);
+
+            let Some(char) = self.source_code.next() else {
+                self.diagnostics.push(Diagnostic {
+                    kind: DiagnosticKind::UnterminatedBlockComment,
+                    span: self.span(start, self.source_code.index()),
+                });
+                break;
+            };
+
+            if char == '*' && self.source_code.peek() == Some('/') {
+                _ = self.source_code.next();
+                break;
+            }
+        }
+
+        let end = self.source_code.index();
+        self.token(PreprocessorTokenKind::Comment(self.cleaned(start, end)), start, end)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rstest::rstest;
+    use super::*;
+    use pretty_assertions::assert_eq;
+    use crate::{
+        LexerOptions,
+        Span,
+        TokenFlags,
+    };
+
+    fn lexer_with_trivia(input: &str) -> Lexer<'_> {
+        Lexer::with_options(input, LexerOptions {
+            trivia: true,
+            ..Default::default()
+        })
+    }
+
+    #[rstest]
+    #[case("// foo", "// foo")]
+    #[case("//", "//")]
+    #[case("/**/", "/**/")]
+    #[case("/* foo */", "/* foo */")]
+    #[case("/***/", "/***/")]
+    #[case("/* // */", "/* // */")]
+    #[case("// /* */", "// /* */")]
+    #[case("/* multi\nline */", "/* multi\nline */")]
+    #[case("// spliced \\\ncomment", "// spliced comment")]
+    #[case("// spliced \\\r\ncomment", "// spliced comment")]
+    #[case("/\\\n/ spliced start", "// spliced start")]
+    #[case("/* spliced end *\\\n/", "/* spliced end */")]
+    fn single(#[case] input: &str, #[case] expected: &str) {
+        let mut lexer = lexer_with_trivia(input);
+        let token = lexer.next();
+        assert_eq!(token, Some(PreprocessorToken {
+            kind: PreprocessorTokenKind::Comment(expected.into()),
+            span: Span::detached(0, input.len()),
+            flags: TokenFlags::START_OF_FILE,
+        }));
+        assert_eq!(lexer.next(), None);
+        assert_eq!(lexer.diagnostics(), &[]);
+    }
+
+    #[test]
+    fn line_comment_excludes_newline() {
+        let actual: Vec<_> = lexer_with_trivia("// foo\nint")
+            .map(|token| token.kind)
+            .collect();
+
+        assert_eq!(actual, [
+            PreprocessorTokenKind::Comment("// foo".into()),
+            PreprocessorTokenKind::Whitespace("\n".into()),
+            PreprocessorTokenKind::Identifier("int".into()),
+        ]);
+    }
+
+    #[rstest]
+    #[case("/", Punctuator::Solidus)]
+    #[case("/=", Punctuator::DivideAssign)]
+    fn not_a_comment(#[case] input: &str, #[case] expected: Punctuator) {
+        let kinds: Vec<_> = Lexer::new(input)
+            .map(|token| token.kind)
+            .collect();
+
+        assert_eq!(kinds, [PreprocessorTokenKind::Punctuator(expected)]);
+    }
+
+    #[test]
+    fn unterminated_block_comment() {
+        let input = "int /* foo";
+        let mut lexer = lexer_with_trivia(input);
+        let tokens: Vec<_> = lexer.by_ref().collect();
+
+        assert_eq!(tokens.last(), Some(&PreprocessorToken {
+            kind: PreprocessorTokenKind::Comment("/* foo".into()),
+            span: Span::detached(4, input.len()),
+            flags: TokenFlags {
+                at_start_of_line: false,
+                has_leading_whitespace: true,
+            },
+        }));
+
+        assert_eq!(lexer.diagnostics(), &[Diagnostic {
+            kind: DiagnosticKind::UnterminatedBlockComment,
+            span: Span::detached(4, input.len()),
+        }]);
+    }
+}
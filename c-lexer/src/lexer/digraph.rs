@@ -0,0 +1,211 @@
+// Copyright (C) 2023 Tristan Gerritsen <tristan@thewoosh.org>
+// All Rights Reserved.
+
+use crate::{
+    Diagnostic,
+    DiagnosticKind,
+    PreprocessorToken,
+    PreprocessorTokenKind,
+    Punctuator,
+};
+
+use super::{
+    source_code::trigraph_at_start_of,
+    Lexer,
+};
+
+impl<'source_code> Lexer<'source_code> {
+    /// Consumes one of the alternative spellings of ISO/IEC 9899 6.4.6p3, if
+    /// there is one at the current position.
+    pub(super) fn consume_digraph(&mut self) -> Option<PreprocessorToken<'source_code>> {
+        let start = self.source_code.index();
+        let reset_position = self.source_code.position();
+
+        let punctuator = match (self.source_code.next(), self.source_code.next()) {
+            // C++ lexes `<::` as `<` and `::`, unless it is followed by a `:`
+            // or `>`, so that `std::vector<::std::string>` works
+            // (\[lex.pptoken\]p3).
+            (Some('<'), Some(':')) if self.options.is_cpp()
+                && self.source_code.peek() == Some(':')
+                && !matches!(self.source_code.peek_second(), Some(':' | '>')) => {
+                self.source_code.restore(reset_position);
+                return None;
+            }
+            (Some('<'), Some(':')) => Punctuator::LeftSquareBracket,
+            (Some(':'), Some('>')) => Punctuator::RightSquareBracket,
+            (Some('<'), Some('%')) => Punctuator::LeftCurlyBracket,
+            (Some('%'), Some('>')) => Punctuator::RightCurlyBracket,
+            (Some('%'), Some(':')) => {
+                let single_position = self.source_code.position();
+                if self.source_code.next() == Some('%') && self.source_code.next() == Some(':') {
+                    Punctuator::DoublePound
+                } else {
+                    self.source_code.restore(single_position);
+                    Punctuator::Pound
+                }
+            }
+            _ => {
+                self.source_code.restore(reset_position);
+                return None;
+            }
+        };
+
+        let end = self.source_code.index();
+        Some(self.token(PreprocessorTokenKind::Digraph(punctuator), start, end))
+    }
+
+    /// Warns when a trigraph sequence is at the current position, but isn't
+    /// replaced because trigraphs aren't enabled.
+    pub(super) fn check_for_ignored_trigraph(&mut self) {
+        if self.options.trigraphs {
+            return;
+        }
+
+        let start = self.source_code.index();
+        if let Some(replacement) = trigraph_at_start_of(self.source_code.remaining()) {
+            self.diagnostics.push(Diagnostic {
+                kind: DiagnosticKind::TrigraphIgnored(replacement),
+                span: self.span(start, start + 3),
+            });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rstest::rstest;
+    use super::*;
+    use crate::LexerOptions;
+    use pretty_assertions::assert_eq;
+    use crate::{
+        Span,
+        TokenFlags,
+    };
+
+    #[rstest]
+    #[case("<:", Punctuator::LeftSquareBracket)]
+    #[case(":>", Punctuator::RightSquareBracket)]
+    #[case("<%", Punctuator::LeftCurlyBracket)]
+    #[case("%>", Punctuator::RightCurlyBracket)]
+    #[case("%:", Punctuator::Pound)]
+    #[case("%:%:", Punctuator::DoublePound)]
+    fn single(#[case] input: &str, #[case] expected: Punctuator) {
+        let mut lexer = Lexer::new(input);
+        assert_eq!(lexer.next(), Some(PreprocessorToken {
+            kind: PreprocessorTokenKind::Digraph(expected),
+            span: Span::detached(0, input.len()),
+            flags: TokenFlags::START_OF_FILE,
+        }));
+        assert_eq!(lexer.next(), None);
+    }
+
+    #[rstest]
+    #[case("%:%", &[
+        PreprocessorTokenKind::Digraph(Punctuator::Pound),
+        PreprocessorTokenKind::Punctuator(Punctuator::Percentage),
+    ])]
+    #[case("<<:", &[
+        PreprocessorTokenKind::Punctuator(Punctuator::LeftBitShift),
+        PreprocessorTokenKind::Punctuator(Punctuator::Colon),
+    ])]
+    #[case("<=", &[
+        PreprocessorTokenKind::Punctuator(Punctuator::LessThanOrEqualTo),
+    ])]
+    #[case("%=", &[
+        PreprocessorTokenKind::Punctuator(Punctuator::ModuloAssign),
+    ])]
+    fn excerpt(#[case] input: &str, #[case] expected: &[PreprocessorTokenKind]) {
+        let actual: Vec<_> = Lexer::new(input)
+            .map(|token| token.kind)
+            .collect();
+
+        assert_eq!(actual, expected);
+    }
+
+    #[rstest]
+    #[case("<::a", &[
+        PreprocessorTokenKind::Punctuator(Punctuator::LessThan),
+        PreprocessorTokenKind::Punctuator(Punctuator::DoubleColon),
+        PreprocessorTokenKind::Identifier("a".into()),
+    ])]
+    #[case("<:::", &[
+        PreprocessorTokenKind::Digraph(Punctuator::LeftSquareBracket),
+        PreprocessorTokenKind::Punctuator(Punctuator::DoubleColon),
+    ])]
+    #[case("<::>", &[
+        PreprocessorTokenKind::Digraph(Punctuator::LeftSquareBracket),
+        PreprocessorTokenKind::Digraph(Punctuator::RightSquareBracket),
+    ])]
+    fn cpp_less_than_scope(#[case] input: &str, #[case] expected: &[PreprocessorTokenKind]) {
+        let options = LexerOptions {
+            language: crate::Language::Cpp,
+            ..Default::default()
+        };
+
+        let actual: Vec<_> = Lexer::with_options(input, options)
+            .map(|token| token.kind)
+            .collect();
+        assert_eq!(actual, expected);
+
+        let actual: Vec<_> = Lexer::new(input)
+            .map(|token| token.kind)
+            .collect();
+        assert_eq!(actual[0], PreprocessorTokenKind::Digraph(Punctuator::LeftSquareBracket));
+    }
+
+    #[rstest]
+    #[case("??=", PreprocessorTokenKind::Punctuator(Punctuator::Pound))]
+    #[case("??(", PreprocessorTokenKind::Punctuator(Punctuator::LeftSquareBracket))]
+    #[case("??)", PreprocessorTokenKind::Punctuator(Punctuator::RightSquareBracket))]
+    #[case("??<", PreprocessorTokenKind::Punctuator(Punctuator::LeftCurlyBracket))]
+    #[case("??>", PreprocessorTokenKind::Punctuator(Punctuator::RightCurlyBracket))]
+    #[case("??'", PreprocessorTokenKind::Punctuator(Punctuator::BitwiseXor))]
+    #[case("??!", PreprocessorTokenKind::Punctuator(Punctuator::BitwiseOr))]
+    #[case("??-", PreprocessorTokenKind::Punctuator(Punctuator::Tilde))]
+    #[case("??=??=", PreprocessorTokenKind::Punctuator(Punctuator::DoublePound))]
+    fn trigraph(#[case] input: &str, #[case] expected: PreprocessorTokenKind) {
+        let options = LexerOptions {
+            trigraphs: true,
+            ..Default::default()
+        };
+
+        let mut lexer = Lexer::with_options(input, options);
+        assert_eq!(lexer.next(), Some(PreprocessorToken {
+            kind: expected,
+            span: Span::detached(0, input.len()),
+            flags: TokenFlags::START_OF_FILE,
+        }));
+        assert_eq!(lexer.next(), None);
+        assert_eq!(lexer.diagnostics(), &[]);
+    }
+
+    #[test]
+    fn trigraph_line_splice() {
+        let options = LexerOptions {
+            trigraphs: true,
+            trivia: true,
+            ..Default::default()
+        };
+
+        let actual: Vec<_> = Lexer::with_options("// a ??/\nb", options)
+            .map(|token| token.kind)
+            .collect();
+
+        assert_eq!(actual, [PreprocessorTokenKind::Comment("// a b".into())]);
+    }
+
+    #[rstest]
+    #[case("??=", 0)]
+    #[case("x ??!", 2)]
+    #[case("\"??/\"", 1)]
+    fn trigraph_ignored(#[case] input: &str, #[case] start: usize) {
+        let mut lexer = Lexer::new(input);
+        lexer.by_ref().for_each(drop);
+
+        let replacement = trigraph_at_start_of(&input[start..]).unwrap();
+        assert_eq!(lexer.diagnostics(), &[Diagnostic {
+            kind: DiagnosticKind::TrigraphIgnored(replacement),
+            span: Span::detached(start, start + 3),
+        }]);
+    }
+}
@@ -0,0 +1,170 @@
+// Copyright (C) 2023 Tristan Gerritsen <tristan@thewoosh.org>
+// All Rights Reserved.
+
+use crate::{
+    HeaderName,
+    HeaderNameForm,
+    PreprocessorToken,
+    PreprocessorTokenKind,
+    Punctuator,
+};
+
+use super::Lexer;
+
+/// Header names (ISO/IEC 9899 6.4.7) are only recognized within `#include`,
+/// `#import` and `#embed` directives, which is tracked by this state.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub(super) enum DirectiveState {
+    /// After the `#` that starts a directive.
+    Directive,
+
+    /// The next token is lexed as a header name, if possible.
+    HeaderNameExpected,
+
+    /// Anywhere else.
+    #[default]
+    None,
+}
+
+impl<'source_code> Lexer<'source_code> {
+    /// Lexes the next `<...>` or `"..."` as a [`HeaderName`], for callers that
+    /// need one outside of the directives that are detected automatically,
+    /// e.g. in `__has_include`. Whitespace and comments before it are
+    /// skipped over as usual.
+    pub fn expect_header_name(&mut self) {
+        self.directive_state = DirectiveState::HeaderNameExpected;
+    }
+
+    /// Consumes a header name if the state expects one and the characters form
+    /// a complete header name. Otherwise, nothing is consumed.
+    pub(super) fn consume_header_name(&mut self) -> Option<PreprocessorToken<'source_code>> {
+        if self.directive_state != DirectiveState::HeaderNameExpected {
+            return None;
+        }
+
+        let start = self.source_code.index();
+        let reset_position = self.source_code.position();
+
+        let Some(header_name) = self.consume_header_name_characters() else {
+            self.source_code.restore(reset_position);
+            return None;
+        };
+
+        let end = self.source_code.index();
+        Some(self.token(PreprocessorTokenKind::HeaderName(header_name), start, end))
+    }
+
+    fn consume_header_name_characters(&mut self) -> Option<HeaderName> {
+        let (form, terminator) = match self.source_code.next()? {
+            '<' => (HeaderNameForm::Angle, '>'),
+            '"' => (HeaderNameForm::Quote, '"'),
+            _ => return None,
+        };
+
+        let mut name = String::new();
+        loop {
+            match self.source_code.next()? {
+                char if char == terminator => break,
+                '\n' | '\r' => return None,
+                char => name.push(char),
+            }
+        }
+
+        Some(HeaderName { name, form })
+    }
+
+    /// Keeps track of whether the lexer is inside a directive that takes a
+    /// header name.
+    pub(super) fn update_directive_state(&mut self, token: &PreprocessorToken<'_>) {
+        self.directive_state = match (&token.kind, self.directive_state) {
+            (PreprocessorTokenKind::Whitespace(whitespace), _) if whitespace.contains('\n') => DirectiveState::None,
+            (PreprocessorTokenKind::Whitespace(..) | PreprocessorTokenKind::Comment(..), state) => state,
+
+            (kind, _) if token.flags.at_start_of_line && kind.punctuator() == Some(Punctuator::Pound) => {
+                DirectiveState::Directive
+            }
+
+            (PreprocessorTokenKind::Identifier(name), DirectiveState::Directive)
+                if matches!(name.as_str(), "include" | "include_next" | "import" | "embed") => {
+                DirectiveState::HeaderNameExpected
+            }
+
+            _ => DirectiveState::None,
+        };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rstest::rstest;
+    use super::*;
+    use pretty_assertions::assert_eq;
+    use crate::{
+        Span,
+        TokenFlags,
+    };
+
+    fn header_names(input: &str) -> Vec<HeaderName> {
+        Lexer::new(input)
+            .filter_map(|token| match token.kind {
+                PreprocessorTokenKind::HeaderName(header_name) => Some(header_name),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[rstest]
+    #[case("#include <stdio.h>", "stdio.h", HeaderNameForm::Angle)]
+    #[case("#include \"foo.h\"", "foo.h", HeaderNameForm::Quote)]
+    #[case("#include<stdio.h>", "stdio.h", HeaderNameForm::Angle)]
+    #[case("  #  include  <sys/types.h>", "sys/types.h", HeaderNameForm::Angle)]
+    #[case("#import <Foundation/Foundation.h>", "Foundation/Foundation.h", HeaderNameForm::Angle)]
+    #[case("#embed \"data.bin\"", "data.bin", HeaderNameForm::Quote)]
+    #[case("#include_next <limits.h>", "limits.h", HeaderNameForm::Angle)]
+    #[case("%:include <stdio.h>", "stdio.h", HeaderNameForm::Angle)]
+    #[case("/* c */ # /* c */ include /* c */ <a.h>", "a.h", HeaderNameForm::Angle)]
+    #[case("#include \"C:\\dir\\a.h\"", "C:\\dir\\a.h", HeaderNameForm::Quote)]
+    #[case("int x;\n#include <a.h>", "a.h", HeaderNameForm::Angle)]
+    fn directive(#[case] input: &str, #[case] name: &str, #[case] form: HeaderNameForm) {
+        assert_eq!(header_names(input), [HeaderName {
+            name: name.into(),
+            form,
+        }]);
+    }
+
+    #[rstest]
+    #[case("a < b > c")]
+    #[case("x #include <a.h>")]
+    #[case("#define include <a.h>")]
+    #[case("#include\n<a.h>")]
+    #[case("#if a<b && c>d")]
+    fn not_a_header_name(#[case] input: &str) {
+        assert_eq!(header_names(input), []);
+    }
+
+    #[test]
+    fn unterminated_falls_back_to_punctuator() {
+        let actual: Vec<_> = Lexer::new("#include <a.h")
+            .map(|token| token.kind)
+            .collect();
+
+        assert_eq!(actual[2], PreprocessorTokenKind::Punctuator(Punctuator::LessThan));
+    }
+
+    #[test]
+    fn expected_by_caller() {
+        let mut lexer = Lexer::new("(<a.h>)");
+        assert_eq!(lexer.next().map(|token| token.kind), Some(PreprocessorTokenKind::Punctuator(Punctuator::LeftParenthesis)));
+
+        lexer.expect_header_name();
+        assert_eq!(lexer.next(), Some(PreprocessorToken {
+            kind: PreprocessorTokenKind::HeaderName(HeaderName {
+                name: "a.h".into(),
+                form: HeaderNameForm::Angle,
+            }),
+            span: Span::detached(1, 6),
+            flags: TokenFlags::default(),
+        }));
+        assert_eq!(lexer.next().map(|token| token.kind), Some(PreprocessorTokenKind::Punctuator(Punctuator::RightParenthesis)));
+    }
+}
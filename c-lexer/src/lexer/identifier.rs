@@ -0,0 +1,385 @@
+// Copyright (C) 2023 Tristan Gerritsen <tristan@thewoosh.org>
+// All Rights Reserved.
+
+use unicode_normalization::UnicodeNormalization;
+
+use crate::{
+    Diagnostic,
+    DiagnosticKind,
+    Keyword,
+    PreprocessorToken,
+    PreprocessorTokenKind,
+    Symbol,
+};
+
+use super::{
+    source_code::{
+        ByteSet,
+        Position,
+    },
+    Lexer,
+};
+
+/// The ASCII characters that may appear in an identifier after the first
+/// character, which are consumed in bulk.
+const ASCII_IDENTIFIER_CONTINUE: ByteSet = ByteSet::from_ranges(&[
+    (b'_', b'_'),
+    (b'a', b'z'),
+    (b'A', b'Z'),
+    (b'0', b'9'),
+]);
+
+impl<'source_code> Lexer<'source_code> {
+    /// Consumes an identifier as described in ISO/IEC 9899 6.4.2 and Annex D,
+    /// in which universal character names are replaced by the character they
+    /// designate. The caller must make sure an identifier starts here.
+    pub(super) fn consume_identifier_or_keyword(&mut self) -> PreprocessorToken<'source_code> {
+        let start = self.source_code.index();
+
+        // Reuse the allocation, as the spelling is only needed to intern it.
+        let mut identifier = std::mem::take(&mut self.identifier_buffer);
+        identifier.clear();
+        self.consume_identifier_characters(&mut identifier);
+
+        let end = self.source_code.index();
+        debug_assert_ne!(start, end);
+
+        if matches!(identifier.as_str(), "L" | "u" | "U" | "u8") && matches!(self.source_code.peek(), Some('\'' | '"')) {
+            self.identifier_buffer = identifier;
+            return self.consume_quoted_literal(start);
+        }
+
+        if self.options.is_cpp()
+            && matches!(identifier.as_str(), "R" | "LR" | "uR" | "UR" | "u8R")
+            && self.source_code.peek() == Some('"') {
+            if let Some(token) = self.consume_raw_string_literal(start) {
+                self.identifier_buffer = identifier;
+                return token;
+            }
+        }
+
+        let symbol = if !identifier.is_ascii() && !unicode_normalization::is_nfc(&identifier) {
+            self.diagnostics.push(Diagnostic {
+                kind: DiagnosticKind::IdentifierNotNormalized,
+                span: self.span(start, end),
+            });
+
+            Symbol::intern(&identifier.nfc().collect::<String>())
+        } else {
+            Symbol::intern(&identifier)
+        };
+
+        self.identifier_buffer = identifier;
+
+        if self.options.security.confusable_identifiers {
+            self.check_confusable_identifier(symbol, start, end);
+        }
+
+        if self.options.pedantic {
+            if let Some(keyword) = self.options.keyword(symbol).filter(Keyword::is_extension) {
+                self.diagnostics.push(Diagnostic {
+                    kind: DiagnosticKind::ExtensionKeyword(keyword),
+                    span: self.span(start, end),
+                });
+            }
+        }
+
+        self.token(PreprocessorTokenKind::Identifier(symbol), start, end)
+    }
+
+    /// Consumes the characters that may continue an identifier, including
+    /// universal character names, and appends them to `identifier`.
+    pub(super) fn consume_identifier_characters(&mut self, identifier: &mut String) {
+        loop {
+            identifier.push_str(self.source_code.consume_ascii_while(&ASCII_IDENTIFIER_CONTINUE));
+
+            match self.source_code.peek() {
+                Some(char) if is_identifier_continue(char) => {
+                    identifier.push(char);
+                    _ = self.source_code.next();
+                }
+
+                Some('$') if self.options.dialect.allows_dollar_in_identifiers() => {
+                    let start = self.source_code.index();
+                    identifier.push('$');
+                    _ = self.source_code.next();
+
+                    if self.options.pedantic {
+                        let end = self.source_code.index();
+                        self.diagnostics.push(Diagnostic {
+                            kind: DiagnosticKind::DollarInIdentifier,
+                            span: self.span(start, end),
+                        });
+                    }
+                }
+
+                Some('\\') => {
+                    let Some((Some(char), after)) = self.lookahead_universal_character_name() else {
+                        break;
+                    };
+
+                    if !is_identifier_continue(char) {
+                        break;
+                    }
+
+                    identifier.push(char);
+                    self.source_code.restore(after);
+                }
+
+                _ => break,
+            }
+        }
+    }
+
+    /// Consumes a `\`, which either starts a universal character name
+    /// (ISO/IEC 9899 6.4.3) or is a stray character.
+    pub(super) fn consume_backslash(&mut self) -> Option<PreprocessorToken<'source_code>> {
+        let start = self.source_code.index();
+
+        match self.lookahead_universal_character_name() {
+            Some((Some(char), _)) if is_identifier_start(char) => {
+                Some(self.consume_identifier_or_keyword())
+            }
+
+            Some((Some(char), after)) => {
+                self.source_code.restore(after);
+                let end = self.source_code.index();
+                Some(self.token(PreprocessorTokenKind::OtherUniversalCharacterName(char), start, end))
+            }
+
+            Some((None, after)) => {
+                self.diagnostics.push(Diagnostic {
+                    kind: DiagnosticKind::InvalidUniversalCharacterName,
+                    span: self.span(start, after.index()),
+                });
+
+                self.consume_single_char_token(PreprocessorTokenKind::NonWhiteSpaceCharacter('\\'))
+            }
+
+            None => self.consume_single_char_token(PreprocessorTokenKind::NonWhiteSpaceCharacter('\\')),
+        }
+    }
+
+    /// Looks for a `\u` followed by four, or a `\U` followed by eight
+    /// hexadecimal digits. If found, the designated character is returned
+    /// (if it is allowed by 6.4.3p2), together with the position after it.
+    /// Nothing is consumed.
+    pub(super) fn lookahead_universal_character_name(&mut self) -> Option<(Option<char>, Position)> {
+        let reset_position = self.source_code.position();
+        let universal_character_name = self.consume_universal_character_name();
+        self.source_code.restore(reset_position);
+        universal_character_name
+    }
+
+    fn consume_universal_character_name(&mut self) -> Option<(Option<char>, Position)> {
+        if self.source_code.next()? != '\\' {
+            return None;
+        }
+
+        let length = match self.source_code.next()? {
+            'u' => 4,
+            'U' => 8,
+            _ => return None,
+        };
+
+        let mut value = 0;
+        for _ in 0..length {
+            value = value * 16 + self.source_code.next()?.to_digit(16)?;
+        }
+
+        let allowed = value >= 0xA0 || matches!(value, 0x24 | 0x40 | 0x60);
+        let char = char::from_u32(value).filter(|_| allowed);
+        Some((char, self.source_code.position()))
+    }
+}
+
+/// Whether the character may start an identifier, as specified by
+/// ISO/IEC 9899 Annex D (XID_Start plus the underscore).
+pub(super) fn is_identifier_start(char: char) -> bool {
+    if char.is_ascii() {
+        return matches!(char, '_' | 'a'..='z' | 'A'..='Z');
+    }
+
+    unicode_ident::is_xid_start(char)
+}
+
+/// Whether the character may appear in an identifier after the first
+/// character, as specified by ISO/IEC 9899 Annex D (XID_Continue).
+pub(super) fn is_identifier_continue(char: char) -> bool {
+    if char.is_ascii() {
+        return matches!(char, '_' | 'a'..='z' | 'A'..='Z' | '0'..='9');
+    }
+
+    unicode_ident::is_xid_continue(char)
+}
+
+#[cfg(test)]
+mod tests {
+    use rstest::rstest;
+    use super::*;
+    use pretty_assertions::assert_eq;
+    use crate::{
+        Dialect,
+        LexerOptions,
+        Span,
+        TokenFlags,
+    };
+
+    #[rstest]
+    #[case("main", "main")]
+    #[case("_x1", "_x1")]
+    #[case("café", "café")]
+    #[case("naïve_変数", "naïve_変数")]
+    #[case("Ωmega", "Ωmega")]
+    #[case("caf\\u00E9", "café")]
+    #[case("caf\\U000000e9", "café")]
+    #[case("\\u00E9t\\u00E9", "été")]
+    #[case("x\\u0300", "x\u{300}")]
+    fn single(#[case] input: &str, #[case] expected: &str) {
+        let mut lexer = Lexer::new(input);
+        assert_eq!(lexer.next(), Some(PreprocessorToken {
+            kind: PreprocessorTokenKind::Identifier(expected.into()),
+            span: Span::detached(0, input.len()),
+            flags: TokenFlags::START_OF_FILE,
+        }));
+        assert_eq!(lexer.next(), None);
+        assert_eq!(lexer.diagnostics(), &[]);
+    }
+
+    #[test]
+    fn universal_character_name_equals_spelled_out_character() {
+        let identifiers: Vec<_> = Lexer::new("café caf\\u00e9")
+            .filter(|token| matches!(token.kind, PreprocessorTokenKind::Identifier(..)))
+            .map(|token| token.kind)
+            .collect();
+
+        assert_eq!(identifiers[0], identifiers[1]);
+    }
+
+    #[test]
+    fn not_normalized() {
+        let input = "cafe\u{301}";
+        let mut lexer = Lexer::new(input);
+
+        assert_eq!(lexer.next(), Some(PreprocessorToken {
+            kind: PreprocessorTokenKind::Identifier("café".into()),
+            span: Span::detached(0, input.len()),
+            flags: TokenFlags::START_OF_FILE,
+        }));
+
+        assert_eq!(lexer.diagnostics(), &[Diagnostic {
+            kind: DiagnosticKind::IdentifierNotNormalized,
+            span: Span::detached(0, input.len()),
+        }]);
+    }
+
+    #[rstest]
+    #[case("\\u0300", &[PreprocessorTokenKind::OtherUniversalCharacterName('\u{300}')])]
+    #[case("\\u00A0", &[PreprocessorTokenKind::OtherUniversalCharacterName('\u{A0}')])]
+    #[case("a\\u00A0", &[
+        PreprocessorTokenKind::Identifier("a".into()),
+        PreprocessorTokenKind::OtherUniversalCharacterName('\u{A0}'),
+    ])]
+    #[case("\\", &[PreprocessorTokenKind::NonWhiteSpaceCharacter('\\')])]
+    #[case("\\u12", &[
+        PreprocessorTokenKind::NonWhiteSpaceCharacter('\\'),
+        PreprocessorTokenKind::Identifier("u12".into()),
+    ])]
+    fn other(#[case] input: &str, #[case] expected: &[PreprocessorTokenKind]) {
+        let actual: Vec<_> = Lexer::new(input)
+            .map(|token| token.kind)
+            .collect();
+
+        assert_eq!(actual, expected);
+    }
+
+    #[rstest]
+    #[case("\\uD800", 6)]
+    #[case("\\U00110000", 10)]
+    #[case("\\u0041", 6)]
+    fn invalid_universal_character_name(#[case] input: &str, #[case] end: usize) {
+        let mut lexer = Lexer::new(input);
+        lexer.by_ref().for_each(drop);
+
+        assert_eq!(lexer.diagnostics(), &[Diagnostic {
+            kind: DiagnosticKind::InvalidUniversalCharacterName,
+            span: Span::detached(0, end),
+        }]);
+    }
+
+    fn options(dialect: Dialect, pedantic: bool) -> LexerOptions {
+        LexerOptions {
+            dialect,
+            pedantic,
+            ..Default::default()
+        }
+    }
+
+    #[rstest]
+    #[case("$x", &[PreprocessorTokenKind::Identifier("$x".into())])]
+    #[case("a$b$", &[PreprocessorTokenKind::Identifier("a$b$".into())])]
+    #[case("__attribute__", &[PreprocessorTokenKind::Identifier("__attribute__".into())])]
+    fn dialect(#[case] input: &str, #[case] expected: &[PreprocessorTokenKind]) {
+        let mut lexer = Lexer::with_options(input, options(Dialect::GNU, false));
+        let actual: Vec<_> = lexer.by_ref()
+            .map(|token| token.kind)
+            .collect();
+
+        assert_eq!(actual, expected);
+        assert_eq!(lexer.diagnostics(), &[]);
+    }
+
+    #[test]
+    fn dollar_without_extensions() {
+        let actual: Vec<_> = Lexer::new("a$b")
+            .map(|token| token.kind)
+            .collect();
+
+        assert_eq!(actual, [
+            PreprocessorTokenKind::Identifier("a".into()),
+            PreprocessorTokenKind::NonWhiteSpaceCharacter('$'),
+            PreprocessorTokenKind::Identifier("b".into()),
+        ]);
+    }
+
+    #[rstest]
+    #[case(Dialect::GNU, "a$b", &[DiagnosticKind::DollarInIdentifier], 1..2)]
+    #[case(Dialect::GNU, " __extension__", &[DiagnosticKind::ExtensionKeyword(Keyword::GnuExtension)], 1..14)]
+    #[case(Dialect::MSVC, "__declspec", &[DiagnosticKind::ExtensionKeyword(Keyword::MsvcDeclspec)], 0..10)]
+    #[case(Dialect::MSVC, "__inline__", &[], 0..0)]
+    #[case(Dialect::GNU, "inline", &[], 0..0)]
+    fn pedantic(
+        #[case] dialect: Dialect,
+        #[case] input: &str,
+        #[case] expected: &[DiagnosticKind],
+        #[case] span: std::ops::Range<usize>,
+    ) {
+        let mut lexer = Lexer::with_options(input, options(dialect, true));
+        lexer.by_ref().for_each(drop);
+
+        let expected: Vec<_> = expected.iter()
+            .map(|kind| Diagnostic {
+                kind: *kind,
+                span: Span::detached(span.start, span.end),
+            })
+            .collect();
+        assert_eq!(lexer.diagnostics(), expected);
+
+        let mut lexer = Lexer::with_options(input, options(dialect, false));
+        lexer.by_ref().for_each(drop);
+        assert_eq!(lexer.diagnostics(), &[]);
+    }
+
+    #[rstest]
+    #[case('a', true, true)]
+    #[case('_', true, true)]
+    #[case('1', false, true)]
+    #[case('é', true, true)]
+    #[case('\u{300}', false, true)]
+    #[case('$', false, false)]
+    #[case('😀', false, false)]
+    fn character_classes(#[case] char: char, #[case] start: bool, #[case] continue_: bool) {
+        assert_eq!(is_identifier_start(char), start);
+        assert_eq!(is_identifier_continue(char), continue_);
+    }
+}
@@ -0,0 +1,238 @@
+// Copyright (C) 2023 Tristan Gerritsen <tristan@thewoosh.org>
+// All Rights Reserved.
+
+use std::ops::Range;
+
+use crate::{
+    FileId,
+    LexerOptions,
+    PreprocessorToken,
+    Span,
+    TokenFlags,
+};
+
+use super::Lexer;
+
+/// A change to source code: the bytes in `range` of the old text are replaced
+/// by `text`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TextEdit {
+    pub range: Range<usize>,
+    pub text: String,
+}
+
+impl TextEdit {
+    pub fn new(range: Range<usize>, text: impl Into<String>) -> Self {
+        Self {
+            range,
+            text: text.into(),
+        }
+    }
+
+    /// Applies the edit to the old text.
+    pub fn apply(&self, source_code: &mut String) {
+        source_code.replace_range(self.range.clone(), &self.text);
+    }
+
+    /// The end of the inserted text in the new text.
+    fn new_end(&self) -> usize {
+        self.range.start + self.text.len()
+    }
+
+    /// Maps an offset after the edit in the new text to the old text.
+    fn old_offset(&self, new_offset: usize) -> usize {
+        new_offset - self.text.len() + self.range.len()
+    }
+
+    fn shift(&self, span: Span) -> Span {
+        let shift = |offset: usize| offset - self.range.len() + self.text.len();
+        Span::new(span.file, shift(span.start()), shift(span.end()))
+    }
+}
+
+impl Lexer<'_> {
+    /// Updates the tokens of the old text to those of `source_code`, the text
+    /// after the edit, and returns the range of `tokens` that changed. The
+    /// tokens must have been lexed with the same options.
+    ///
+    /// Only the tokens around the edit are lexed again. Lexing restarts at the
+    /// last token before the edit that is at the start of a line, where the
+    /// lexer has no state besides the flags of that token, and which is not
+    /// affected by the edit. It stops at the first token at the start of a
+    /// line after the edit that is the same as the old token at that
+    /// position, from where on the old tokens are still valid. Edits that
+    /// open or close a block comment or a literal thus relex until the
+    /// streams meet again, which might be the end of the file.
+    ///
+    /// Diagnostics aren't reported, use a [`Lexer`] for those.
+    pub fn relex(
+        tokens: &mut Vec<PreprocessorToken<'static>>,
+        source_code: &str,
+        edit: &TextEdit,
+        options: LexerOptions,
+    ) -> Range<usize> {
+        let file = tokens.first().map_or(FileId::DETACHED, |token| token.span.file);
+
+        let first_after_edit = tokens.partition_point(|token| token.span.start() < edit.range.start);
+        // A backslash (or a `??/` trigraph) might become a line splice and
+        // join the whitespace before it.
+        let restart = tokens[..first_after_edit]
+            .iter()
+            .rposition(|token| {
+                token.flags.at_start_of_line
+                    && !matches!(source_code.as_bytes()[token.span.start()], b'\\' | b'?')
+            });
+
+        let (first, start, flags) = match restart {
+            Some(index) => (index, tokens[index].span.start(), tokens[index].flags),
+            None => (0, 0, TokenFlags::START_OF_FILE),
+        };
+
+        let mut lexer = Lexer::with_file(source_code, file, options);
+        lexer.source_code.seek(start);
+        lexer.next_flags = flags;
+
+        let mut old_index = first;
+        let mut old_end = tokens.len();
+        let mut relexed = Vec::new();
+
+        for token in lexer {
+            if token.flags.at_start_of_line && token.span.start() >= edit.new_end() {
+                let old_start = edit.old_offset(token.span.start());
+                old_index += tokens[old_index..].partition_point(|token| token.span.start() < old_start);
+
+                let is_same = tokens.get(old_index).is_some_and(|old| {
+                    old.span.start() == old_start
+                        && old.span.len() == token.span.len()
+                        && old.flags == token.flags
+                        && old.kind == token.kind
+                });
+
+                if is_same {
+                    old_end = old_index;
+                    break;
+                }
+            }
+
+            relexed.push(token.into_owned());
+        }
+
+        for token in &mut tokens[old_end..] {
+            token.span = edit.shift(token.span);
+        }
+
+        let count = relexed.len();
+        tokens.splice(first..old_end, relexed);
+        first..first + count
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rstest::rstest;
+    use super::*;
+    use pretty_assertions::assert_eq;
+    use crate::PreprocessorTokenKind;
+
+    fn lex(source_code: &str, options: &LexerOptions) -> Vec<PreprocessorToken<'static>> {
+        Lexer::with_options(source_code, options.clone())
+            .map(PreprocessorToken::into_owned)
+            .collect()
+    }
+
+    /// Relexes incrementally and checks the result against lexing the new
+    /// text from scratch, returning the changed range.
+    fn relex(old: &str, edit: TextEdit, options: LexerOptions) -> (Vec<PreprocessorToken<'static>>, Range<usize>) {
+        let mut tokens = lex(old, &options);
+
+        let mut new = old.to_string();
+        edit.apply(&mut new);
+
+        let changed = Lexer::relex(&mut tokens, &new, &edit, options.clone());
+        assert_eq!(tokens, lex(&new, &options));
+
+        (tokens, changed)
+    }
+
+    fn trivia_options() -> LexerOptions {
+        LexerOptions {
+            trivia: true,
+            ..Default::default()
+        }
+    }
+
+    #[rstest]
+    #[case("int a;\nint b;\nint c;\n", TextEdit::new(11..11, "x"))]
+    #[case("int a;\nint b;\nint c;\n", TextEdit::new(7..14, ""))]
+    #[case("int a;\nint b;\nint c;\n", TextEdit::new(0..0, "long "))]
+    #[case("int a;\nint b;\nint c;\n", TextEdit::new(21..21, "int d;"))]
+    #[case("int a;\nint b;\nint c;\n", TextEdit::new(10..11, "/*"))]
+    #[case("int a;\nint /* b;\nint */ c;\n", TextEdit::new(11..13, ""))]
+    #[case("int a;\nint /* b;\nint c;\n", TextEdit::new(24..24, "*/"))]
+    #[case("int a;\nint b;\nint c;\n", TextEdit::new(11..11, "\""))]
+    #[case("char *a = \"x\";\nint b;\n", TextEdit::new(12..13, ""))]
+    #[case("int a;\nint b;\nint c;\n", TextEdit::new(6..7, " "))]
+    #[case("int a;\nint b;\nint c;\n", TextEdit::new(6..6, "\\"))]
+    #[case("int a; \\\nint b;\n", TextEdit::new(7..8, ""))]
+    #[case("#include <a.h>\n#include <b.h>\n", TextEdit::new(0..1, ""))]
+    #[case("#define x\n#include <b.h>\n", TextEdit::new(3..9, "include"))]
+    #[case("", TextEdit::new(0..0, "int a;"))]
+    #[case("int a;", TextEdit::new(0..6, ""))]
+    #[case("  int a;\n  int b;", TextEdit::new(1..1, "\n"))]
+    #[case(" \\", TextEdit::new(2..2, "\n"))]
+    #[case("a;\n\\u00e9", TextEdit::new(4..9, "\n"))]
+    fn matches_full_relex(#[case] old: &str, #[case] edit: TextEdit, #[values(false, true)] trivia: bool) {
+        relex(old, edit, LexerOptions {
+            trivia,
+            ..Default::default()
+        });
+    }
+
+    #[test]
+    fn only_relexes_the_edited_line() {
+        let (tokens, changed) = relex("int a;\nint b;\nint c;\n", TextEdit::new(11..12, "xyz"), LexerOptions::default());
+
+        assert_eq!(changed, 3..6);
+        assert_eq!(tokens[4].kind, PreprocessorTokenKind::Identifier("xyz".into()));
+        assert_eq!(tokens[7].span, Span::detached(20, 21));
+    }
+
+    #[test]
+    fn opening_block_comment_relexes_until_it_closes() {
+        let old = "a;\nb;\nc; */\nd;\ne;\n";
+        let (tokens, changed) = relex(old, TextEdit::new(3..3, "/*"), trivia_options());
+
+        assert_eq!(tokens[3].kind, PreprocessorTokenKind::Comment("/*b;\nc; */".into()));
+        assert_eq!(tokens[changed.end].kind, PreprocessorTokenKind::Identifier("d".into()));
+    }
+
+    #[test]
+    fn closing_string_literal() {
+        let old = "a = \"x;\nb;\n";
+        let (tokens, changed) = relex(old, TextEdit::new(6..6, "\""), LexerOptions::default());
+
+        assert_eq!(changed, 0..4);
+        assert_eq!(tokens[2].kind, PreprocessorTokenKind::StringLiteral("\"x\"".into()));
+    }
+
+    proptest::proptest! {
+        #[test]
+        fn matches_full_relex_for_any_edit(
+            old in "[a-c /*\"'\\\\\n\r#<>?=()u0-9]{0,40}",
+            start in 0..40usize,
+            length in 0..8usize,
+            text in "[a-c /*\"'\\\\\n\r#<>?=()u0-9]{0,8}",
+            trivia: bool,
+            trigraphs: bool,
+        ) {
+            let start = start.min(old.len());
+            let end = (start + length).min(old.len());
+
+            relex(&old, TextEdit::new(start..end, text), LexerOptions {
+                trivia,
+                trigraphs,
+                ..Default::default()
+            });
+        }
+    }
+}
@@ -0,0 +1,312 @@
+// Copyright (C) 2023 Tristan Gerritsen <tristan@thewoosh.org>
+// All Rights Reserved.
+
+use std::borrow::Cow;
+
+use crate::{
+    Diagnostic,
+    DiagnosticKind,
+    PreprocessorToken,
+    PreprocessorTokenKind,
+};
+
+use super::{
+    identifier::is_identifier_start,
+    source_code::ByteSet,
+    Lexer,
+};
+
+/// The characters in the body of a literal that need to be looked at, the
+/// others are skipped in bulk.
+const CHARACTER_CONSTANT_STOP: ByteSet = ByteSet::new(b"'\\\n\r?");
+const STRING_LITERAL_STOP: ByteSet = ByteSet::new(b"\"\\\n\r?");
+
+/// The maximum length of the delimiter of a C++ raw string literal, see
+/// \[lex.string\]p2.
+const MAX_RAW_STRING_DELIMITER_LENGTH: usize = 16;
+
+impl<'source_code> Lexer<'source_code> {
+    /// Consumes a character constant or string literal, of which the encoding
+    /// prefix (if any) was already consumed. Escape sequences are only
+    /// skipped over here, they are decoded when converting to a
+    /// [`Token`][crate::Token].
+    pub(super) fn consume_quoted_literal(&mut self, start: usize) -> PreprocessorToken<'source_code> {
+        let Some(quote) = self.source_code.next() else {
+            unreachable!("caller must make sure a quote follows");
+        };
+
+        let stop = if quote == '"' { &STRING_LITERAL_STOP } else { &CHARACTER_CONSTANT_STOP };
+        let mut terminated = false;
+
+        loop {
+            self.source_code.skip_until(stop);
+
+            let Some(char) = self.source_code.peek() else {
+                break;
+            };
+
+            if matches!(char, '\n' | '\r') {
+                break;
+            }
+
+            if char == '?' {
+                self.check_for_ignored_trigraph();
+            }
+
+            _ = self.source_code.next();
+
+            if char == quote {
+                terminated = true;
+                break;
+            }
+
+            // Skip over the escaped character, which might be the quote.
+            if char == '\\' && self.source_code.peek().is_some_and(|c| !matches!(c, '\n' | '\r')) {
+                _ = self.source_code.next();
+            }
+        }
+
+        if terminated {
+            self.consume_ud_suffix();
+        }
+
+        let end = self.source_code.index();
+
+        if !terminated {
+            self.diagnostics.push(Diagnostic {
+                kind: if quote == '"' {
+                    DiagnosticKind::UnterminatedStringLiteral
+                } else {
+                    DiagnosticKind::UnterminatedCharacterConstant
+                },
+                span: self.span(start, end),
+            });
+        }
+
+        let spelling = self.cleaned(start, end);
+        let kind = if quote == '"' {
+            PreprocessorTokenKind::StringLiteral(spelling)
+        } else {
+            PreprocessorTokenKind::CharacterConstant(spelling)
+        };
+
+        self.token(kind, start, end)
+    }
+
+    /// Consumes a C++ raw string literal `R"delimiter(...)delimiter"`, of
+    /// which the prefix up to and including the `R` was already consumed.
+    /// Line splices and trigraphs in the raw characters are reverted, so they
+    /// are scanned as written (C++ \[lex.pptoken\]p3.1). Nothing is consumed
+    /// if the delimiter is invalid, as it isn't a raw string literal then.
+    pub(super) fn consume_raw_string_literal(&mut self, start: usize) -> Option<PreprocessorToken<'source_code>> {
+        let reset_position = self.source_code.position();
+        if self.source_code.next() != Some('"') {
+            unreachable!("caller must make sure a quote follows");
+        }
+
+        let text = self.source_code.as_str();
+        let raw_start = self.source_code.index();
+
+        let delimiter_length = text.as_bytes()[raw_start..]
+            .iter()
+            .take(MAX_RAW_STRING_DELIMITER_LENGTH + 1)
+            .position(|byte| *byte == b'(');
+
+        let delimiter = match delimiter_length {
+            Some(length) => &text[raw_start..raw_start + length],
+            None => "",
+        };
+
+        if delimiter_length.is_none() || !delimiter.bytes().all(is_raw_string_delimiter_character) {
+            self.source_code.restore(reset_position);
+            return None;
+        }
+
+        let body_start = raw_start + delimiter.len() + 1;
+        let terminator = format!("){delimiter}\"");
+
+        let raw_end = match text[body_start..].find(&terminator) {
+            Some(index) => {
+                let raw_end = body_start + index + terminator.len();
+                self.source_code.seek(raw_end);
+                self.consume_ud_suffix();
+                raw_end
+            }
+
+            None => {
+                self.source_code.skip_to_end();
+                self.diagnostics.push(Diagnostic {
+                    kind: DiagnosticKind::UnterminatedStringLiteral,
+                    span: self.span(start, text.len()),
+                });
+                text.len()
+            }
+        };
+
+        let end = self.source_code.index();
+
+        let spelling = match (self.cleaned(start, raw_start), self.cleaned(raw_end, end)) {
+            (Cow::Borrowed(..), Cow::Borrowed(..)) => Cow::Borrowed(&text[start..end]),
+            (prefix, suffix) => Cow::Owned(format!("{prefix}{}{suffix}", &text[raw_start..raw_end])),
+        };
+
+        Some(self.token(PreprocessorTokenKind::StringLiteral(spelling), start, end))
+    }
+
+    /// Consumes the identifier directly after a C++ literal, which makes it a
+    /// user-defined literal (C++ \[lex.ext\]).
+    fn consume_ud_suffix(&mut self) {
+        if !self.options.is_cpp() || !self.source_code.peek().is_some_and(is_identifier_start) {
+            return;
+        }
+
+        let mut suffix = std::mem::take(&mut self.identifier_buffer);
+        self.consume_identifier_characters(&mut suffix);
+        suffix.clear();
+        self.identifier_buffer = suffix;
+    }
+}
+
+/// Whether the byte may appear in the delimiter of a raw string literal,
+/// which excludes spaces, parentheses, backslashes and control characters.
+fn is_raw_string_delimiter_character(byte: u8) -> bool {
+    byte.is_ascii_graphic() && !matches!(byte, b'(' | b')' | b'\\')
+}
+
+#[cfg(test)]
+mod tests {
+    use rstest::rstest;
+    use super::*;
+    use pretty_assertions::assert_eq;
+    use crate::{
+        Span,
+        TokenFlags,
+    };
+
+    #[rstest]
+    #[case("'a'", PreprocessorTokenKind::CharacterConstant("'a'".into()))]
+    #[case("'\\''", PreprocessorTokenKind::CharacterConstant("'\\''".into()))]
+    #[case("L'x'", PreprocessorTokenKind::CharacterConstant("L'x'".into()))]
+    #[case("u8'x'", PreprocessorTokenKind::CharacterConstant("u8'x'".into()))]
+    #[case("\"\"", PreprocessorTokenKind::StringLiteral("\"\"".into()))]
+    #[case("\"a\\\"b\"", PreprocessorTokenKind::StringLiteral("\"a\\\"b\"".into()))]
+    #[case("u8\"x\"", PreprocessorTokenKind::StringLiteral("u8\"x\"".into()))]
+    #[case("u\"x\"", PreprocessorTokenKind::StringLiteral("u\"x\"".into()))]
+    #[case("U\"x\"", PreprocessorTokenKind::StringLiteral("U\"x\"".into()))]
+    #[case("L\"x\"", PreprocessorTokenKind::StringLiteral("L\"x\"".into()))]
+    #[case("\"a\\\nb\"", PreprocessorTokenKind::StringLiteral("\"ab\"".into()))]
+    fn single(#[case] input: &str, #[case] expected: PreprocessorTokenKind) {
+        let mut lexer = Lexer::new(input);
+        assert_eq!(lexer.next(), Some(PreprocessorToken {
+            kind: expected,
+            span: Span::detached(0, input.len()),
+            flags: TokenFlags::START_OF_FILE,
+        }));
+        assert_eq!(lexer.next(), None);
+        assert_eq!(lexer.diagnostics(), &[]);
+    }
+
+    #[rstest]
+    #[case("x\"a\"", &[
+        PreprocessorTokenKind::Identifier("x".into()),
+        PreprocessorTokenKind::StringLiteral("\"a\"".into()),
+    ])]
+    #[case("u8 'a'", &[
+        PreprocessorTokenKind::Identifier("u8".into()),
+        PreprocessorTokenKind::CharacterConstant("'a'".into()),
+    ])]
+    fn prefix_must_be_adjacent(#[case] input: &str, #[case] expected: &[PreprocessorTokenKind]) {
+        let actual: Vec<_> = Lexer::new(input)
+            .map(|token| token.kind)
+            .collect();
+
+        assert_eq!(actual, expected);
+    }
+
+    fn cpp_options() -> crate::LexerOptions {
+        crate::LexerOptions {
+            language: crate::Language::Cpp,
+            ..Default::default()
+        }
+    }
+
+    #[rstest]
+    #[case("R\"(a)\"")]
+    #[case("R\"(a\\)\"")]
+    #[case("R\"x(a)\")x\"")]
+    #[case("R\"(line\nline)\"")]
+    #[case("R\"(a\\\nb)\"")]
+    #[case("R\"(??/)\"")]
+    #[case("u8R\"(a)\"")]
+    #[case("LR\"(a)\"")]
+    #[case("uR\"(a)\"")]
+    #[case("UR\"(a)\"")]
+    #[case("R\"0123456789abcdef()0123456789abcdef\"")]
+    #[case("\"abc\"_s")]
+    #[case("'a'_c")]
+    #[case("R\"(a)\"_r")]
+    fn cpp_string_literal(#[case] input: &str) {
+        let mut lexer = Lexer::with_options(input, crate::LexerOptions {
+            trigraphs: true,
+            ..cpp_options()
+        });
+        let token = lexer.next().unwrap();
+        assert_eq!(token.span, Span::detached(0, input.len()));
+        assert_eq!(token.kind.to_string(), input);
+        assert_eq!(lexer.next(), None);
+        assert_eq!(lexer.diagnostics(), &[]);
+    }
+
+    #[rstest]
+    #[case("R\"(a)\"", &[
+        PreprocessorTokenKind::Identifier("R".into()),
+        PreprocessorTokenKind::StringLiteral("\"(a)\"".into()),
+    ])]
+    #[case("\"abc\"_s", &[
+        PreprocessorTokenKind::StringLiteral("\"abc\"".into()),
+        PreprocessorTokenKind::Identifier("_s".into()),
+    ])]
+    fn cpp_string_literal_in_c(#[case] input: &str, #[case] expected: &[PreprocessorTokenKind]) {
+        let actual: Vec<_> = Lexer::new(input)
+            .map(|token| token.kind)
+            .collect();
+
+        assert_eq!(actual, expected);
+    }
+
+    /// An invalid delimiter means it isn't a raw string literal at all.
+    #[rstest]
+    #[case("R\"a b(x)a b\"")]
+    #[case("R\"0123456789abcdefg(x)0123456789abcdefg\"")]
+    #[case("R\"\\(x)\\\"")]
+    fn invalid_raw_string_delimiter(#[case] input: &str) {
+        let mut lexer = Lexer::with_options(input, cpp_options());
+        assert_eq!(lexer.next().map(|token| token.kind), Some(PreprocessorTokenKind::Identifier("R".into())));
+    }
+
+    #[test]
+    fn unterminated_raw_string_literal() {
+        let input = "R\"x(a)\"\nb";
+        let mut lexer = Lexer::with_options(input, cpp_options());
+        assert_eq!(lexer.next().map(|token| token.kind), Some(PreprocessorTokenKind::StringLiteral(input.into())));
+        assert_eq!(lexer.next(), None);
+        assert_eq!(lexer.diagnostics(), &[Diagnostic {
+            kind: DiagnosticKind::UnterminatedStringLiteral,
+            span: Span::detached(0, input.len()),
+        }]);
+    }
+
+    #[rstest]
+    #[case("\"abc\nint", "\"abc", DiagnosticKind::UnterminatedStringLiteral)]
+    #[case("'a", "'a", DiagnosticKind::UnterminatedCharacterConstant)]
+    #[case("\"abc\\", "\"abc\\", DiagnosticKind::UnterminatedStringLiteral)]
+    fn unterminated(#[case] input: &str, #[case] spelling: &str, #[case] kind: DiagnosticKind) {
+        let mut lexer = Lexer::new(input);
+        let token = lexer.next().unwrap();
+        assert_eq!(token.span.end(), spelling.len());
+        assert_eq!(lexer.diagnostics(), &[Diagnostic {
+            kind,
+            span: Span::detached(0, spelling.len()),
+        }]);
+    }
+}
@@ -1,45 +1,192 @@
 // Copyright (C) 2023 Tristan Gerritsen <tristan@thewoosh.org>
 // All Rights Reserved.
 
+use std::borrow::Cow;
+
+mod comment;
+mod digraph;
+mod header_name;
+mod identifier;
+mod incremental;
+mod literal;
+mod number;
+mod options;
+mod parallel;
+mod security;
 mod source_code;
+mod stream;
+mod streaming;
+mod whitespace;
 
 use crate::{
+    DecodedSource,
+    Diagnostic,
+    FileId,
     PreprocessorToken,
-    PreprocessorTokenKind, Punctuator,
+    PreprocessorTokenKind,
+    Punctuator,
+    Span,
+    TokenFlags,
 };
 
-use self::source_code::SourceCode;
+pub use self::incremental::TextEdit;
+pub use self::stream::{
+    Checkpoint,
+    TokenStream,
+};
+pub use self::parallel::{
+    LexedFile,
+    ParallelLexer,
+};
+pub use self::streaming::StreamingLexer;
+pub use self::options::{
+    Dialect,
+    Language,
+    LanguageStandard,
+    LexerOptions,
+    SecurityChecks,
+    UnknownLanguage,
+    UnknownLanguageStandard,
+};
+
+use self::{
+    header_name::DirectiveState,
+    identifier::is_identifier_start,
+    security::IdentifierSkeletons,
+    source_code::SourceCode,
+    whitespace::is_whitespace,
+};
 
+/// Splits source code into [`PreprocessorToken`]s (ISO/IEC 9899 6.4).
+///
+/// In [trivia mode][LexerOptions::trivia], the lexer is lossless: the spans
+/// of the tokens are contiguous and cover the whole source code, so
+/// concatenating the [spelling][Lexer::spelling] of every token reproduces
+/// the input byte-for-byte. This holds for any input, including stray
+/// characters, line splices and unterminated comments and literals.
 pub struct Lexer<'source_code> {
     source_code: SourceCode<'source_code>,
+    file: FileId,
+    options: LexerOptions,
+    diagnostics: Vec<Diagnostic>,
+    directive_state: DirectiveState,
+    identifier_buffer: String,
+    identifier_skeletons: IdentifierSkeletons,
+
+    /// The flags of the next token, as gathered from the whitespace and
+    /// comments before it.
+    next_flags: TokenFlags,
 }
 
 impl<'source_code> Lexer<'source_code> {
     pub fn new(source_code: &'source_code str) -> Self {
+        Self::with_options(source_code, LexerOptions::default())
+    }
+
+    pub fn with_options(source_code: &'source_code str, options: LexerOptions) -> Self {
+        Self::with_file(source_code, FileId::DETACHED, options)
+    }
+
+    /// Creates a lexer of which the tokens refer to the given file, see also
+    /// [`SourceMap::lexer`][crate::SourceMap::lexer].
+    pub fn with_file(source_code: &'source_code str, file: FileId, options: LexerOptions) -> Self {
         Self {
-            source_code: source_code.into()
+            source_code: SourceCode::new(source_code, options.trigraphs),
+            file,
+            options,
+            diagnostics: Vec::new(),
+            directive_state: DirectiveState::default(),
+            identifier_buffer: String::new(),
+            identifier_skeletons: IdentifierSkeletons::default(),
+            next_flags: TokenFlags::START_OF_FILE,
+        }
+    }
+
+    /// Creates a lexer for the text of bytes that were already decoded,
+    /// which reports the problems found whilst decoding them as well.
+    ///
+    /// The spans of the tokens and diagnostics are offsets in the decoded
+    /// text, not in the bytes, which differ after a byte order mark, a CRLF
+    /// line ending or an invalid sequence. They can be mapped back to the
+    /// bytes using [`DecodedSource::original_span`].
+    pub fn from_decoded(source: &'source_code DecodedSource<'_>, options: LexerOptions) -> Self {
+        Self::with_options(source.text(), options)
+            .with_diagnostics(source.diagnostics().to_vec())
+    }
+
+    pub(crate) fn with_diagnostics(mut self, diagnostics: Vec<Diagnostic>) -> Self {
+        self.diagnostics = diagnostics;
+        self
+    }
+
+    pub fn options(&self) -> &LexerOptions {
+        &self.options
+    }
+
+    /// The exact text of the token in the source code, including any line
+    /// splices and trigraphs.
+    pub fn spelling(&self, token: &PreprocessorToken<'_>) -> &'source_code str {
+        &self.source_code.as_str()[token.span.range()]
+    }
+
+    /// The spelling of the token after translation phases 1 and 2, i.e. with
+    /// trigraphs replaced (if enabled) and line splices removed.
+    pub fn cleaned_spelling(&self, token: &PreprocessorToken<'_>) -> Cow<'source_code, str> {
+        self.cleaned(token.span.start(), token.span.end())
+    }
+
+    /// The diagnostics that were reported for the tokens consumed so far.
+    pub fn diagnostics(&self) -> &[Diagnostic] {
+        &self.diagnostics
+    }
+
+    /// Takes the diagnostics that were reported so far, so that consumers
+    /// can handle them whilst lexing.
+    pub fn take_diagnostics(&mut self) -> Vec<Diagnostic> {
+        std::mem::take(&mut self.diagnostics)
+    }
+
+    fn span(&self, start: usize, end: usize) -> Span {
+        Span::new(self.file, start, end)
+    }
+
+    fn token(&self, kind: PreprocessorTokenKind<'source_code>, start: usize, end: usize) -> PreprocessorToken<'source_code> {
+        PreprocessorToken {
+            kind,
+            span: self.span(start, end),
+            flags: self.next_flags,
         }
     }
 
+    /// The text between the offsets after translation phases 1 and 2, which
+    /// is only copied if it contains a line splice or trigraph.
+    fn cleaned(&self, start: usize, end: usize) -> Cow<'source_code, str> {
+        let raw = &self.source_code.as_str()[start..end];
+
+        let has_splice = raw.contains("\\\n") || raw.contains("\\\r");
+        let has_trigraph = self.options.trigraphs && raw.contains("??");
+        if !has_splice && !has_trigraph {
+            return Cow::Borrowed(raw);
+        }
+
+        Cow::Owned(SourceCode::new(raw, self.options.trigraphs).collect())
+    }
+
     fn consume_single_char_token(
         &mut self,
-        kind: PreprocessorTokenKind,
-    ) -> Option<PreprocessorToken> {
+        kind: PreprocessorTokenKind<'source_code>,
+    ) -> Option<PreprocessorToken<'source_code>> {
         let start = self.source_code.index();
         _ = self.source_code.next()?;
         let end = self.source_code.index();
 
-        Some(PreprocessorToken {
-            kind,
-            start,
-            end,
-        })
+        Some(self.token(kind, start, end))
     }
 
     fn consume_single_char_punctuator(
         &mut self,
         punctuator: Punctuator,
-    ) -> Option<PreprocessorToken> {
+    ) -> Option<PreprocessorToken<'source_code>> {
         let kind = PreprocessorTokenKind::Punctuator(punctuator);
         self.consume_single_char_token(kind)
     }
@@ -49,25 +196,19 @@ impl<'source_code> Lexer<'source_code> {
         char: char,
         single: Punctuator,
         double: Punctuator
-    ) -> PreprocessorToken {
+    ) -> PreprocessorToken<'source_code> {
         let start = self.source_code.index();
         _ = self.source_code.next();
 
         if self.source_code.peek() == Some(char) {
             _ = self.source_code.next();
 
-            return PreprocessorToken {
-                kind: PreprocessorTokenKind::Punctuator(double),
-                start,
-                end: self.source_code.index(),
-            };
+            let end = self.source_code.index();
+            return self.token(PreprocessorTokenKind::Punctuator(double), start, end);
         }
 
-        PreprocessorToken {
-            kind: PreprocessorTokenKind::Punctuator(single),
-            start,
-            end: self.source_code.index(),
-        }
+        let end = self.source_code.index();
+        self.token(PreprocessorTokenKind::Punctuator(single), start, end)
     }
 
     fn consume_three_possible_punctuators(
@@ -77,11 +218,11 @@ impl<'source_code> Lexer<'source_code> {
         second_kind: Option<Punctuator>,
         third_char: char,
         third_kind: Punctuator,
-    ) -> PreprocessorToken {
+    ) -> PreprocessorToken<'source_code> {
         let start = self.source_code.index();
 
         _ = self.source_code.next();
-        let source_code_reset = self.source_code.clone();
+        let reset_position = self.source_code.position();
 
         if self.source_code.peek() == Some(second_char) {
             _ = self.source_code.next();
@@ -91,30 +232,22 @@ impl<'source_code> Lexer<'source_code> {
                 let kind = PreprocessorTokenKind::Punctuator(third_kind);
                 let end = self.source_code.index();
 
-                return PreprocessorToken {
-                    kind,
-                    start,
-                    end,
-                };
+                return self.token(kind, start, end);
             }
 
             if let Some(second_kind) = second_kind {
                 let kind = PreprocessorTokenKind::Punctuator(second_kind);
                 let end = self.source_code.index();
 
-                return PreprocessorToken {
-                    kind,
-                    start,
-                    end,
-                };
+                return self.token(kind, start, end);
             }
         }
 
-        self.source_code = source_code_reset;
+        self.source_code.restore(reset_position);
 
         let kind = PreprocessorTokenKind::Punctuator(default);
         let end = self.source_code.index();
-        PreprocessorToken { kind, start, end }
+        self.token(kind, start, end)
     }
 
     fn consume_math_or_affix_or_assignment(
@@ -123,7 +256,7 @@ impl<'source_code> Lexer<'source_code> {
         math: Punctuator,
         affix: Punctuator,
         assignment: Punctuator
-    ) -> PreprocessorToken {
+    ) -> PreprocessorToken<'source_code> {
         let start = self.source_code.index();
         _ = self.source_code.next();
 
@@ -131,57 +264,64 @@ impl<'source_code> Lexer<'source_code> {
             _ = self.source_code.next();
             let end = self.source_code.index();
             let kind = PreprocessorTokenKind::Punctuator(affix);
-            return PreprocessorToken { kind, start, end };
+            return self.token(kind, start, end);
         }
 
         if self.source_code.peek() == Some('=') {
             _ = self.source_code.next();
             let end = self.source_code.index();
             let kind = PreprocessorTokenKind::Punctuator(assignment);
-            return PreprocessorToken { kind, start, end };
+            return self.token(kind, start, end);
         }
 
         let end = self.source_code.index();
         let kind = PreprocessorTokenKind::Punctuator(math);
-        PreprocessorToken { kind, start, end }
+        self.token(kind, start, end)
     }
+}
 
-    fn consume_identifier_or_keyword(&mut self) -> PreprocessorToken {
-        let start = self.source_code.index();
-
-        while let Some(token) = self.source_code.peek() {
-            if !matches!(token, '_' | 'a'..='z' | 'A'..='Z' | '0'..='9') {
-                break;
-            }
-
-            _ = self.source_code.next();
-        }
+impl<'source_code> Iterator for Lexer<'source_code> {
+    type Item = PreprocessorToken<'source_code>;
 
-        let end = self.source_code.index();
-        debug_assert_ne!(start, end);
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let token = self.consume_token()?;
+            self.update_directive_state(&token);
 
-        let str = &self.source_code.as_str()[start..end];
+            if self.options.security.bidirectional_control_characters {
+                self.check_bidirectional_control_characters(&token);
+            }
 
-        PreprocessorToken {
-            kind: PreprocessorTokenKind::Identifier(str.into()),
-            start,
-            end,
+            let is_trivia = self.update_next_flags(&token);
+            if !is_trivia || self.options.trivia {
+                return Some(token);
+            }
         }
     }
 }
 
-impl<'source_code> Iterator for Lexer<'source_code> {
-    type Item = PreprocessorToken;
-
-    fn next(&mut self) -> Option<Self::Item> {
+impl<'source_code> Lexer<'source_code> {
+    fn consume_token(&mut self) -> Option<PreprocessorToken<'source_code>> {
         let start = self.source_code.index();
-        let first_char = self.source_code.peek()?;
+        let Some(first_char) = self.source_code.peek() else {
+            return self.consume_dangling_line_splices();
+        };
+
+        if let Some(token) = self.consume_header_name() {
+            return Some(token);
+        }
+
+        if matches!(first_char, '<' | ':' | '%') {
+            if let Some(token) = self.consume_digraph() {
+                return Some(token);
+            }
+        }
 
         match first_char {
-            ' ' | '\n' | '\t' | '\r' => self.consume_single_char_token(PreprocessorTokenKind::Whitespace(first_char)),
+            _ if is_whitespace(first_char) => Some(self.consume_whitespace()),
 
             '[' => self.consume_single_char_punctuator(Punctuator::LeftSquareBracket),
-            ']' => self.consume_single_char_punctuator(Punctuator::LeftSquareBracket),
+            ']' => self.consume_single_char_punctuator(Punctuator::RightSquareBracket),
 
             '(' => self.consume_single_char_punctuator(Punctuator::LeftParenthesis),
             ')' => self.consume_single_char_punctuator(Punctuator::RightParenthesis),
@@ -189,6 +329,17 @@ impl<'source_code> Iterator for Lexer<'source_code> {
             '{' => self.consume_single_char_punctuator(Punctuator::LeftCurlyBracket),
             '}' => self.consume_single_char_punctuator(Punctuator::RightCurlyBracket),
 
+            '.' if self.source_code.peek_second().is_some_and(|c| c.is_ascii_digit()) => {
+                Some(self.consume_pp_number())
+            }
+
+            '.' if self.options.is_cpp() && self.source_code.peek_second() == Some('*') => {
+                _ = self.source_code.next();
+                _ = self.source_code.next();
+                let end = self.source_code.index();
+                Some(self.token(PreprocessorTokenKind::Punctuator(Punctuator::PointerToMemberOperator), start, end))
+            }
+
             '.' => Some(self.consume_three_possible_punctuators(
                     Punctuator::FullStop,
                     '.',
@@ -198,17 +349,24 @@ impl<'source_code> Iterator for Lexer<'source_code> {
             )),
 
             '-' => {
-                let reset_source_code = self.source_code.clone();
+                let reset_position = self.source_code.position();
                 _ = self.source_code.next();
 
                 if self.source_code.peek() == Some('>') {
                     _ = self.source_code.next();
-                    let kind = PreprocessorTokenKind::Punctuator(Punctuator::PointerMemberAccessOperator);
+
+                    let mut punctuator = Punctuator::PointerMemberAccessOperator;
+                    if self.options.is_cpp() && self.source_code.peek() == Some('*') {
+                        _ = self.source_code.next();
+                        punctuator = Punctuator::PointerToMemberPointerOperator;
+                    }
+
+                    let kind = PreprocessorTokenKind::Punctuator(punctuator);
                     let end = self.source_code.index();
-                    return Some(PreprocessorToken { kind, start, end });
+                    return Some(self.token(kind, start, end));
                 }
 
-                self.source_code = reset_source_code;
+                self.source_code.restore(reset_position);
 
                 Some(self.consume_math_or_affix_or_assignment(
                     '-',
@@ -233,11 +391,7 @@ impl<'source_code> Iterator for Lexer<'source_code> {
                 Punctuator::NotEqualTo,
             )),
 
-            '/' => Some(self.consume_two_possible_punctuators(
-                '=',
-                Punctuator::Solidus,
-                Punctuator::DivideAssign,
-            )),
+            '/' => Some(self.consume_comment_or_solidus()),
 
             '*' => Some(self.consume_two_possible_punctuators(
                 '=',
@@ -265,7 +419,10 @@ impl<'source_code> Iterator for Lexer<'source_code> {
                 Punctuator::BitwiseAndAssign,
             )),
 
-            '?' => self.consume_single_char_punctuator(Punctuator::QuestionMark),
+            '?' => {
+                self.check_for_ignored_trigraph();
+                self.consume_single_char_punctuator(Punctuator::QuestionMark)
+            }
 
             ':' => Some(self.consume_two_possible_punctuators(
                 ':',
@@ -274,6 +431,7 @@ impl<'source_code> Iterator for Lexer<'source_code> {
             )),
 
             ';' => self.consume_single_char_punctuator(Punctuator::Semicolon),
+            ',' => self.consume_single_char_punctuator(Punctuator::Comma),
 
             '=' => Some(self.consume_two_possible_punctuators(
                 '=',
@@ -283,21 +441,25 @@ impl<'source_code> Iterator for Lexer<'source_code> {
 
             '<' => {
                 let start = self.source_code.index();
-                let reset_source_code = self.source_code.clone();
+                let reset_position = self.source_code.position();
                 {
                     _ = self.source_code.next();
 
                     if self.source_code.peek() == Some('=') {
                         _ = self.source_code.next();
-                        return Some(PreprocessorToken {
-                            kind: PreprocessorTokenKind::Punctuator(Punctuator::LessThanOrEqualTo),
-                            start,
-                            end: self.source_code.index(),
-                        })
+
+                        let mut punctuator = Punctuator::LessThanOrEqualTo;
+                        if self.options.is_cpp() && self.source_code.peek() == Some('>') {
+                            _ = self.source_code.next();
+                            punctuator = Punctuator::ThreeWayComparison;
+                        }
+
+                        let end = self.source_code.index();
+                        return Some(self.token(PreprocessorTokenKind::Punctuator(punctuator), start, end))
                     }
                 }
 
-                self.source_code = reset_source_code;
+                self.source_code.restore(reset_position);
 
                 Some(self.consume_three_possible_punctuators(
                     Punctuator::LessThan,
@@ -310,21 +472,18 @@ impl<'source_code> Iterator for Lexer<'source_code> {
 
             '>' => {
                 let start = self.source_code.index();
-                let reset_source_code = self.source_code.clone();
+                let reset_position = self.source_code.position();
                 {
                     _ = self.source_code.next();
 
                     if self.source_code.peek() == Some('=') {
                         _ = self.source_code.next();
-                        return Some(PreprocessorToken {
-                            kind: PreprocessorTokenKind::Punctuator(Punctuator::GreaterThanOrEqualTo),
-                            start,
-                            end: self.source_code.index(),
-                        })
+                        let end = self.source_code.index();
+                        return Some(self.token(PreprocessorTokenKind::Punctuator(Punctuator::GreaterThanOrEqualTo), start, end))
                     }
                 }
 
-                self.source_code = reset_source_code;
+                self.source_code.restore(reset_position);
 
                 Some(self.consume_three_possible_punctuators(
                     Punctuator::GreaterThan,
@@ -347,15 +506,24 @@ impl<'source_code> Iterator for Lexer<'source_code> {
                 Punctuator::DoublePound,
             )),
 
+            '0'..='9' => Some(self.consume_pp_number()),
+
+            '\'' | '"' => Some(self.consume_quoted_literal(start)),
+
             '_' | 'a'..='z' | 'A'..='Z' => Some(self.consume_identifier_or_keyword()),
 
-            _ => {
-                eprintln!("[lexer] Unknown token: {first_char} U+{:X}", first_char as u32);
+            '$' if self.options.dialect.allows_dollar_in_identifiers() => Some(self.consume_identifier_or_keyword()),
 
-                self.consume_single_char_token(
-                    PreprocessorTokenKind::NonWhiteSpaceCharacter(first_char)
-                )
-            }
+            '\\' => self.consume_backslash(),
+
+            _ if is_identifier_start(first_char) => Some(self.consume_identifier_or_keyword()),
+
+            // This is a valid preprocessing token, e.g. a `@` can still be
+            // stringized by a macro, so it is only diagnosed when it is
+            // converted to a Token.
+            _ => self.consume_single_char_token(
+                PreprocessorTokenKind::NonWhiteSpaceCharacter(first_char)
+            ),
         }
     }
 }
@@ -365,12 +533,9 @@ mod tests {
     use rstest::rstest;
     use super::*;
     use pretty_assertions::assert_eq;
+    use crate::Span;
 
     #[rstest]
-    #[case(" ", PreprocessorTokenKind::Whitespace(' '))]
-    #[case("\r", PreprocessorTokenKind::Whitespace('\r'))]
-    #[case("\n", PreprocessorTokenKind::Whitespace('\n'))]
-    #[case("\t", PreprocessorTokenKind::Whitespace('\t'))]
     #[case("main", PreprocessorTokenKind::Identifier("main".into()))]
     #[case("&&", PreprocessorTokenKind::Punctuator(Punctuator::LogicalAnd))]
     #[case("<<=", PreprocessorTokenKind::Punctuator(Punctuator::LeftBitShiftAssign))]
@@ -383,21 +548,12 @@ mod tests {
     }
 
     #[rstest]
-    #[case(
-        "    ",
-        &[
-            PreprocessorTokenKind::Whitespace(' '),
-            PreprocessorTokenKind::Whitespace(' '),
-            PreprocessorTokenKind::Whitespace(' '),
-            PreprocessorTokenKind::Whitespace(' '),
-        ]
-    )]
+    #[case("    ", &[])]
     #[case(
         "int main",
         &[
 
             PreprocessorTokenKind::Identifier("int".into()),
-            PreprocessorTokenKind::Whitespace(' '),
             PreprocessorTokenKind::Identifier("main".into()),
         ]
     )]
@@ -406,7 +562,6 @@ mod tests {
         &[
 
             PreprocessorTokenKind::Identifier("int".into()),
-            PreprocessorTokenKind::Whitespace(' '),
             PreprocessorTokenKind::Identifier("main".into()),
             PreprocessorTokenKind::Punctuator(Punctuator::LeftParenthesis),
             PreprocessorTokenKind::Punctuator(Punctuator::RightParenthesis),
@@ -418,14 +573,10 @@ mod tests {
 }",
         &[
             PreprocessorTokenKind::Identifier("int".into()),
-            PreprocessorTokenKind::Whitespace(' '),
             PreprocessorTokenKind::Identifier("main".into()),
             PreprocessorTokenKind::Punctuator(Punctuator::LeftParenthesis),
             PreprocessorTokenKind::Punctuator(Punctuator::RightParenthesis),
-            PreprocessorTokenKind::Whitespace(' '),
             PreprocessorTokenKind::Punctuator(Punctuator::LeftCurlyBracket),
-            PreprocessorTokenKind::Whitespace('\n'),
-            PreprocessorTokenKind::Whitespace('\n'),
             PreprocessorTokenKind::Punctuator(Punctuator::RightCurlyBracket),
         ]
     )]
@@ -436,4 +587,173 @@ mod tests {
 
         assert_eq!(expected, actual);
     }
+
+    #[rstest]
+    #[case("ma\\\nin", "main")]
+    #[case("ma\\\r\nin", "main")]
+    #[case("\\\nmain", "main")]
+    #[case("main\\\n", "main")]
+    fn line_splice_in_identifier(#[case] input: &str, #[case] expected: &str) {
+        let mut lexer = Lexer::new(input);
+        let token = lexer.next().unwrap();
+        assert_eq!(token, PreprocessorToken {
+            kind: PreprocessorTokenKind::Identifier(expected.into()),
+            span: Span::detached(0, input.len()),
+            flags: TokenFlags::START_OF_FILE,
+        });
+        assert_eq!(lexer.cleaned_spelling(&token), expected);
+        assert_eq!(lexer.next(), None);
+    }
+
+    #[test]
+    fn line_splice_between_punctuator_characters() {
+        let actual: Vec<_> = Lexer::new("-\\\n> +\\\n=")
+            .map(|token| token.kind)
+            .collect();
+
+        assert_eq!(actual, [
+            PreprocessorTokenKind::Punctuator(Punctuator::PointerMemberAccessOperator),
+            PreprocessorTokenKind::Punctuator(Punctuator::AddAssign),
+        ]);
+    }
+
+    #[test]
+    fn offsets_refer_to_original_text() {
+        let input = "a \\\nb";
+        let tokens: Vec<_> = Lexer::new(input)
+            .map(|token| token.span.range())
+            .collect();
+
+        assert_eq!(tokens, [0..1, 2..5]);
+    }
+
+    #[test]
+    fn recovers_after_problems() {
+        let mut lexer = Lexer::new("/* a */ @ \"b\n'c");
+        let kinds: Vec<_> = lexer.by_ref()
+            .map(|token| token.kind)
+            .collect();
+
+        assert_eq!(kinds.len(), 3);
+        assert_eq!(kinds[0], PreprocessorTokenKind::NonWhiteSpaceCharacter('@'));
+
+        let diagnostics: Vec<_> = lexer.take_diagnostics()
+            .into_iter()
+            .map(|diagnostic| diagnostic.kind)
+            .collect();
+        assert_eq!(diagnostics, [
+            crate::DiagnosticKind::UnterminatedStringLiteral,
+            crate::DiagnosticKind::UnterminatedCharacterConstant,
+        ]);
+        assert_eq!(lexer.diagnostics(), &[]);
+    }
+
+    #[test]
+    fn from_decoded() {
+        let source = DecodedSource::decode(b"\xEF\xBB\xBFint\r\n\xFFa;", crate::InputCharset::Utf8);
+        let mut lexer = Lexer::from_decoded(&source, LexerOptions::default());
+        let tokens: Vec<_> = lexer.by_ref().collect();
+
+        assert_eq!(tokens[0].kind, PreprocessorTokenKind::Identifier("int".into()));
+        assert_eq!(tokens[1].kind, PreprocessorTokenKind::NonWhiteSpaceCharacter('\u{FFFD}'));
+        assert_eq!(tokens[1].flags, crate::TokenFlags {
+            at_start_of_line: true,
+            has_leading_whitespace: true,
+        });
+        assert_eq!(source.original_span(tokens[2].span), Span::detached(9, 10));
+
+        assert_eq!(lexer.diagnostics(), &[crate::Diagnostic {
+            kind: crate::DiagnosticKind::InvalidEncoding {
+                charset: crate::InputCharset::Utf8,
+                offset: 8,
+            },
+            span: Span::detached(4, 7),
+        }]);
+    }
+
+    #[rstest]
+    #[case("\"a\\n\"", true)]
+    #[case("1.5e+3", true)]
+    #[case("// comment", true)]
+    #[case(" \t ", true)]
+    #[case("'\\n'", true)]
+    #[case("\"a\\\nb\"", false)]
+    #[case("1\\\r\n2", false)]
+    #[case(" \\\n ", false)]
+    fn payload_borrows_unless_cleaned(#[case] input: &str, #[case] borrowed: bool) {
+        let options = LexerOptions {
+            trivia: true,
+            ..Default::default()
+        };
+
+        let token = Lexer::with_options(input, options).next().unwrap();
+        let (PreprocessorTokenKind::StringLiteral(spelling)
+            | PreprocessorTokenKind::CharacterConstant(spelling)
+            | PreprocessorTokenKind::PpNumber(spelling)
+            | PreprocessorTokenKind::Comment(spelling)
+            | PreprocessorTokenKind::Whitespace(spelling)) = &token.kind else {
+            panic!("unexpected token {token:?}");
+        };
+
+        assert_eq!(matches!(spelling, Cow::Borrowed(..)), borrowed);
+
+        let owned = token.clone().into_owned();
+        assert_eq!(owned, token);
+    }
+
+    fn assert_lossless(input: &str, trigraphs: bool) {
+        let options = LexerOptions {
+            trigraphs,
+            trivia: true,
+            ..Default::default()
+        };
+
+        let mut lexer = Lexer::with_options(input, options);
+        let mut end = 0;
+        let mut round_trip = String::new();
+
+        while let Some(token) = lexer.next() {
+            assert_eq!(token.span.start(), end, "gap or overlap before {token:?} in {input:?}");
+            assert!(!token.span.is_empty(), "empty token {token:?} in {input:?}");
+            round_trip.push_str(lexer.spelling(&token));
+            end = token.span.end();
+        }
+
+        assert_eq!(round_trip, input);
+    }
+
+    #[rstest]
+    #[case("")]
+    #[case("int main() { return 0; }\n")]
+    #[case("@ $ ` \u{0} \u{7F} \u{A0} 😀")]
+    #[case("\\\n")]
+    #[case("\\\n\\\r\n")]
+    #[case("a\\\n")]
+    #[case("\\")]
+    #[case("\\u12")]
+    #[case("\\uD800")]
+    #[case("/* unterminated")]
+    #[case("\"unterminated\n'also")]
+    #[case("// comment \\\n continued")]
+    #[case("#include <unterminated\n#include \"a.h\"")]
+    #[case("??= ??/\n ??")]
+    #[case("<: :> <% %> %: %:%: %:%")]
+    #[case("1.e+5'a'.. ... ->")]
+    #[case("u8'a' L\"b\" U u")]
+    fn lossless(#[case] input: &str) {
+        assert_lossless(input, false);
+        assert_lossless(input, true);
+    }
+
+    proptest::proptest! {
+        #[test]
+        fn lossless_for_any_input(input in proptest::prelude::any::<String>(), trigraphs: bool) {
+            assert_lossless(&input, trigraphs);
+        }
+
+        #[test]
+        fn lossless_for_c_like_input(input in "([a-zA-Z0-9_ \t\r\n\\\\?'\"/*<>%:#.+=uUL@é-]|\\\\\n){0,64}", trigraphs: bool) {
+            assert_lossless(&input, trigraphs);
+        }
+    }
 }
@@ -0,0 +1,151 @@
+// Copyright (C) 2023 Tristan Gerritsen <tristan@thewoosh.org>
+// All Rights Reserved.
+
+use crate::{
+    PreprocessorToken,
+    PreprocessorTokenKind,
+};
+
+use super::{
+    identifier::is_identifier_continue,
+    source_code::ByteSet,
+    Lexer,
+};
+
+/// The characters that continue a pp-number, except for the exponent
+/// characters that may be followed by a sign.
+const ASCII_PP_NUMBER_CONTINUE: ByteSet = ByteSet::from_ranges(&[
+    (b'.', b'.'),
+    (b'0', b'9'),
+    (b'_', b'_'),
+    (b'a', b'd'),
+    (b'f', b'o'),
+    (b'q', b'z'),
+    (b'A', b'D'),
+    (b'F', b'O'),
+    (b'Q', b'Z'),
+]);
+
+impl<'source_code> Lexer<'source_code> {
+    /// Consumes a pp-number as described in ISO/IEC 9899 6.4.8. The caller
+    /// must make sure it starts with a digit, or a `.` followed by a digit.
+    pub(super) fn consume_pp_number(&mut self) -> PreprocessorToken<'source_code> {
+        let start = self.source_code.index();
+
+        loop {
+            _ = self.source_code.consume_ascii_while(&ASCII_PP_NUMBER_CONTINUE);
+
+            let Some(char) = self.source_code.peek() else {
+                break;
+            };
+
+            match char {
+                'e' | 'E' | 'p' | 'P' => {
+                    _ = self.source_code.next();
+
+                    if let Some('+' | '-') = self.source_code.peek() {
+                        _ = self.source_code.next();
+                    }
+                }
+
+                // C23 digit separator, which must be followed by a digit or
+                // nondigit. Before C23, the `'` starts a character constant.
+                '\'' if self.options.allows_digit_separators() => {
+                    let Some(next) = self.source_code.peek_second() else {
+                        break;
+                    };
+
+                    if !is_identifier_continue(next) {
+                        break;
+                    }
+
+                    _ = self.source_code.next();
+                    _ = self.source_code.next();
+                }
+
+                '.' => {
+                    _ = self.source_code.next();
+                }
+
+                _ if is_identifier_continue(char) => {
+                    _ = self.source_code.next();
+                }
+
+                _ => break,
+            }
+        }
+
+        let end = self.source_code.index();
+        self.token(PreprocessorTokenKind::PpNumber(self.cleaned(start, end)), start, end)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rstest::rstest;
+    use super::*;
+    use pretty_assertions::assert_eq;
+    use crate::{
+        Language,
+        LanguageStandard,
+        LexerOptions,
+        Span,
+        TokenFlags,
+    };
+
+    #[rstest]
+    #[case("0")]
+    #[case("1234")]
+    #[case(".5")]
+    #[case("1.")]
+    #[case("1.2.3")]
+    #[case("1e+5")]
+    #[case("1E-5")]
+    #[case("0x1p-3")]
+    #[case("0x1P+3f")]
+    #[case("0b1010")]
+    #[case("1'000'000")]
+    #[case("0xFFull")]
+    #[case("1_000")]
+    #[case("12abc")]
+    fn single(#[case] input: &str) {
+        let mut lexer = Lexer::new(input);
+        assert_eq!(lexer.next(), Some(PreprocessorToken {
+            kind: PreprocessorTokenKind::PpNumber(input.into()),
+            span: Span::detached(0, input.len()),
+            flags: TokenFlags::START_OF_FILE,
+        }));
+        assert_eq!(lexer.next(), None);
+    }
+
+    #[rstest]
+    #[case("1+2", &["1", "+", "2"])]
+    #[case("0x1e+2", &["0x1e+2"])]
+    #[case("1a+2", &["1a", "+", "2"])]
+    #[case("1'", &["1", "'"])]
+    #[case("a.5", &["a", ".5"])]
+    #[case("1\\\n0", &["10"])]
+    fn boundaries(#[case] input: &str, #[case] expected: &[&str]) {
+        let actual: Vec<_> = Lexer::new(input)
+            .map(|token| match token.kind {
+                PreprocessorTokenKind::PpNumber(spelling) => spelling,
+                _ => input[token.span.range()].into(),
+            })
+            .collect();
+
+        assert_eq!(actual, expected);
+    }
+
+    #[rstest]
+    #[case(Language::C, LanguageStandard::C17, &["1", "'0'"])]
+    #[case(Language::C, LanguageStandard::C23, &["1'0", "'"])]
+    #[case(Language::Cpp, LanguageStandard::C17, &["1'0", "'"])]
+    fn digit_separators(#[case] language: Language, #[case] standard: LanguageStandard, #[case] expected: &[&str]) {
+        let input = "1'0'";
+        let actual: Vec<_> = Lexer::with_options(input, LexerOptions { language, standard, ..Default::default() })
+            .map(|token| &input[token.span.range()])
+            .collect();
+
+        assert_eq!(actual, expected);
+    }
+}
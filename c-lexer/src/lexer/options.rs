@@ -0,0 +1,343 @@
+// Copyright (C) 2023 Tristan Gerritsen <tristan@thewoosh.org>
+// All Rights Reserved.
+
+use std::{
+    error::Error,
+    fmt::Display,
+    str::FromStr,
+};
+
+use crate::{
+    Keyword,
+    Punctuator,
+    Symbol,
+};
+
+/// Configures how the [`Lexer`][crate::Lexer] and the conversion to
+/// [`Token`][crate::Token]s interpret the source code.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct LexerOptions {
+    pub language: Language,
+
+    /// The revision of C, which is ignored for C++.
+    pub standard: LanguageStandard,
+
+    /// Replace trigraph sequences (ISO/IEC 9899 5.2.1.1) like `??=`, as
+    /// enabled by `-trigraphs`. When disabled, the lexer warns about them.
+    pub trigraphs: bool,
+
+    /// Produce [`Whitespace`][crate::PreprocessorTokenKind::Whitespace] and
+    /// [`Comment`][crate::PreprocessorTokenKind::Comment] tokens, e.g. for
+    /// formatters. Otherwise, they are only reflected in the
+    /// [`TokenFlags`][crate::TokenFlags] of the token after them.
+    pub trivia: bool,
+
+    /// The compiler extensions that are enabled.
+    pub dialect: Dialect,
+
+    /// Warn whenever an extension of the [`Dialect`] is used, as `-pedantic`
+    /// does.
+    pub pedantic: bool,
+
+    /// Warn about source code that is displayed differently than it is
+    /// compiled, as in Trojan Source attacks.
+    pub security: SecurityChecks,
+}
+
+impl LexerOptions {
+    /// Whether the identifier is a keyword under these options, and if so,
+    /// which one.
+    pub fn keyword(&self, identifier: Symbol) -> Option<Keyword> {
+        identifier.keyword()
+            .filter(|keyword| match self.language {
+                _ if keyword.is_extension() => keyword.is_keyword_in_dialect(self.dialect),
+                Language::C => keyword.is_keyword_in(self.standard),
+                Language::Cpp => keyword.is_keyword_in_cpp(),
+            })
+    }
+
+    /// The punctuator the identifier is an alternative token for, e.g. `&&`
+    /// for `and`, which only exist in C++ (\[lex.digraph\]). In C, these are
+    /// macros defined by `<iso646.h>`.
+    pub fn alternative_token(&self, identifier: Symbol) -> Option<Punctuator> {
+        if self.language != Language::Cpp {
+            return None;
+        }
+
+        Some(match identifier.as_str() {
+            "and" => Punctuator::LogicalAnd,
+            "and_eq" => Punctuator::BitwiseAndAssign,
+            "bitand" => Punctuator::ReferenceOperatorOrBitwiseAnd,
+            "bitor" => Punctuator::BitwiseOr,
+            "compl" => Punctuator::Tilde,
+            "not" => Punctuator::ExclamationMark,
+            "not_eq" => Punctuator::NotEqualTo,
+            "or" => Punctuator::LogicalOr,
+            "or_eq" => Punctuator::BitwiseOrAssign,
+            "xor" => Punctuator::BitwiseXor,
+            "xor_eq" => Punctuator::BitwiseXorAssign,
+            _ => return None,
+        })
+    }
+
+    /// Whether `'` separates digits in pp-numbers, as in `1'000`, which is
+    /// new in C23 (6.4.8) and C++14.
+    pub fn allows_digit_separators(&self) -> bool {
+        self.is_cpp() || self.standard >= LanguageStandard::C23
+    }
+
+    pub const fn is_cpp(&self) -> bool {
+        matches!(self.language, Language::Cpp)
+    }
+}
+
+/// The language to lex, as selected by `-x`. C++ is lexed as of C++20, so
+/// headers that are shared between C and C++ can be scanned as either.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub enum Language {
+    #[default]
+    C,
+
+    /// Adds the keywords, alternative tokens and punctuators of C++, raw
+    /// string literals and user-defined literal suffixes.
+    Cpp,
+}
+
+impl FromStr for Language {
+    type Err = UnknownLanguage;
+
+    /// Parses the value of `-x`, e.g. `c++`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "c" | "c-header" => Ok(Self::C),
+            "c++" | "c++-header" => Ok(Self::Cpp),
+            _ => Err(UnknownLanguage),
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub struct UnknownLanguage;
+
+impl Display for UnknownLanguage {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("UnknownLanguage")
+    }
+}
+
+impl Error for UnknownLanguage {
+}
+
+/// The lexical extensions of GCC and MSVC to ISO C and C++ that real-world
+/// headers depend on. Both can be enabled at the same time, like Clang does
+/// with `-std=gnu23 -fms-extensions`.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Dialect {
+    /// The GNU extensions of `-std=gnu*`: `$` in identifiers and the
+    /// `__attribute__`, `__asm__`, `__extension__`, `__inline__`,
+    /// `__restrict` and `__int128` keywords.
+    pub gnu: bool,
+
+    /// The Microsoft extensions of `-fms-extensions`: `$` in identifiers and
+    /// the `__declspec` and `__restrict` keywords.
+    pub msvc: bool,
+}
+
+impl Dialect {
+    /// Only ISO C or C++, without extensions.
+    pub const ISO: Self = Self {
+        gnu: false,
+        msvc: false,
+    };
+
+    pub const GNU: Self = Self {
+        gnu: true,
+        msvc: false,
+    };
+
+    pub const MSVC: Self = Self {
+        gnu: false,
+        msvc: true,
+    };
+
+    /// Whether `$` may appear in identifiers.
+    pub const fn allows_dollar_in_identifiers(&self) -> bool {
+        self.gnu || self.msvc
+    }
+}
+
+/// Checks against source code that looks different to a reviewer than to the
+/// compiler (CVE-2021-42574). They are disabled by default; enable them with
+/// [`SecurityChecks::ALL`] and opt out of single diagnostics by clearing
+/// their field.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct SecurityChecks {
+    /// Warn about Unicode bidirectional control characters in comments,
+    /// character constants and string literals, which can reorder how the
+    /// code around them is displayed.
+    pub bidirectional_control_characters: bool,
+
+    /// Warn about identifiers in the same file that look alike, i.e. that
+    /// have the same skeleton as defined by Unicode UTS #39. Identifiers that
+    /// are both ASCII are never reported, so `l1` and `ll` don't clash.
+    pub confusable_identifiers: bool,
+}
+
+impl SecurityChecks {
+    pub const NONE: Self = Self {
+        bidirectional_control_characters: false,
+        confusable_identifiers: false,
+    };
+
+    pub const ALL: Self = Self {
+        bidirectional_control_characters: true,
+        confusable_identifiers: true,
+    };
+}
+
+/// The revision of ISO/IEC 9899 to follow, as selected by `-std=`.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum LanguageStandard {
+    /// ISO/IEC 9899:1990, also known as ANSI C.
+    C89,
+
+    /// ISO/IEC 9899:1999
+    C99,
+
+    /// ISO/IEC 9899:2011
+    C11,
+
+    /// ISO/IEC 9899:2018
+    C17,
+
+    /// ISO/IEC 9899:2024
+    #[default]
+    C23,
+}
+
+impl FromStr for LanguageStandard {
+    type Err = UnknownLanguageStandard;
+
+    /// Parses the value of `-std=`, e.g. `c99`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "c89" | "c90" | "iso9899:1990" => Ok(Self::C89),
+            "c99" | "iso9899:1999" => Ok(Self::C99),
+            "c11" | "iso9899:2011" => Ok(Self::C11),
+            "c17" | "c18" | "iso9899:2017" | "iso9899:2018" => Ok(Self::C17),
+            "c23" | "c2x" | "iso9899:2024" => Ok(Self::C23),
+            _ => Err(UnknownLanguageStandard),
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub struct UnknownLanguageStandard;
+
+impl Display for UnknownLanguageStandard {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("UnknownLanguageStandard")
+    }
+}
+
+impl Error for UnknownLanguageStandard {
+}
+
+#[cfg(test)]
+mod tests {
+    use rstest::rstest;
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[rstest]
+    #[case("c89", Ok(LanguageStandard::C89))]
+    #[case("c90", Ok(LanguageStandard::C89))]
+    #[case("c99", Ok(LanguageStandard::C99))]
+    #[case("c11", Ok(LanguageStandard::C11))]
+    #[case("c17", Ok(LanguageStandard::C17))]
+    #[case("c18", Ok(LanguageStandard::C17))]
+    #[case("c23", Ok(LanguageStandard::C23))]
+    #[case("c2x", Ok(LanguageStandard::C23))]
+    #[case("c++17", Err(UnknownLanguageStandard))]
+    fn language_standard_from_str(
+        #[case] input: &str,
+        #[case] expected: Result<LanguageStandard, UnknownLanguageStandard>,
+    ) {
+        assert_eq!(input.parse(), expected);
+    }
+
+    #[rstest]
+    #[case(LanguageStandard::C89, "inline", None)]
+    #[case(LanguageStandard::C99, "inline", Some(Keyword::Inline))]
+    #[case(LanguageStandard::C17, "bool", None)]
+    #[case(LanguageStandard::C17, "_Bool", Some(Keyword::UnderscoreBool))]
+    #[case(LanguageStandard::C23, "bool", Some(Keyword::Bool))]
+    #[case(LanguageStandard::C23, "main", None)]
+    fn keyword(#[case] standard: LanguageStandard, #[case] identifier: &str, #[case] expected: Option<Keyword>) {
+        let options = LexerOptions {
+            standard,
+            ..Default::default()
+        };
+        assert_eq!(options.keyword(identifier.into()), expected);
+    }
+
+    #[rstest]
+    #[case("class", Some(Keyword::Class))]
+    #[case("bool", Some(Keyword::Bool))]
+    #[case("_Bool", None)]
+    #[case("restrict", None)]
+    #[case("and", None)]
+    fn cpp_keyword(#[case] identifier: &str, #[case] expected: Option<Keyword>) {
+        let options = LexerOptions {
+            language: Language::Cpp,
+            standard: LanguageStandard::C89,
+            ..Default::default()
+        };
+        assert_eq!(options.keyword(identifier.into()), expected);
+    }
+
+    #[rstest]
+    #[case(Dialect::ISO, "__attribute__", None)]
+    #[case(Dialect::GNU, "__attribute__", Some(Keyword::GnuAttribute))]
+    #[case(Dialect::GNU, "__int128", Some(Keyword::GnuInt128))]
+    #[case(Dialect::GNU, "__declspec", None)]
+    #[case(Dialect::MSVC, "__declspec", Some(Keyword::MsvcDeclspec))]
+    #[case(Dialect::MSVC, "__restrict", Some(Keyword::GnuRestrict))]
+    #[case(Dialect::MSVC, "__extension__", None)]
+    #[case(Dialect::GNU, "int", Some(Keyword::Int))]
+    fn dialect_keyword(
+        #[case] dialect: Dialect,
+        #[case] identifier: &str,
+        #[case] expected: Option<Keyword>,
+        #[values(Language::C, Language::Cpp)] language: Language,
+    ) {
+        let options = LexerOptions {
+            language,
+            dialect,
+            ..Default::default()
+        };
+        assert_eq!(options.keyword(identifier.into()), expected);
+    }
+
+    #[rstest]
+    #[case(Language::Cpp, "and", Some(Punctuator::LogicalAnd))]
+    #[case(Language::Cpp, "xor_eq", Some(Punctuator::BitwiseXorAssign))]
+    #[case(Language::Cpp, "andy", None)]
+    #[case(Language::C, "and", None)]
+    fn alternative_token(#[case] language: Language, #[case] identifier: &str, #[case] expected: Option<Punctuator>) {
+        let options = LexerOptions {
+            language,
+            ..Default::default()
+        };
+        assert_eq!(options.alternative_token(identifier.into()), expected);
+    }
+
+    #[rstest]
+    #[case("c", Ok(Language::C))]
+    #[case("c++", Ok(Language::Cpp))]
+    #[case("c++-header", Ok(Language::Cpp))]
+    #[case("objective-c", Err(UnknownLanguage))]
+    fn language_from_str(#[case] input: &str, #[case] expected: Result<Language, UnknownLanguage>) {
+        assert_eq!(input.parse(), expected);
+    }
+}
@@ -0,0 +1,201 @@
+// Copyright (C) 2023 Tristan Gerritsen <tristan@thewoosh.org>
+// All Rights Reserved.
+
+use std::{
+    io,
+    num::NonZeroUsize,
+    path::Path,
+    sync::atomic::{
+        AtomicUsize,
+        Ordering,
+    },
+};
+
+use crate::{
+    source_map::SourceFile,
+    Diagnostic,
+    FileId,
+    InputCharset,
+    LexerOptions,
+    PreprocessorToken,
+    SourceMap,
+};
+
+/// Loads and lexes many files at once, on a pool of threads that take the
+/// next file as soon as they are done with the last one. The identifiers of
+/// all files are interned into the same [`Symbol`][crate::Symbol] interner,
+/// and the files are added to the same [`SourceMap`].
+///
+/// The results are in the order of the files that were given, regardless of
+/// the order in which they were lexed, and the [`FileId`]s are assigned in
+/// that order too, so the output doesn't depend on scheduling.
+#[derive(Clone, Debug)]
+pub struct ParallelLexer {
+    options: LexerOptions,
+    threads: NonZeroUsize,
+}
+
+/// The tokens and diagnostics of a file lexed by a [`ParallelLexer`].
+#[derive(Clone, Debug, PartialEq)]
+pub struct LexedFile<'source_map> {
+    pub file: FileId,
+    pub tokens: Vec<PreprocessorToken<'source_map>>,
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+impl ParallelLexer {
+    /// Creates a lexer that uses a thread for every core.
+    pub fn new(options: LexerOptions) -> Self {
+        Self {
+            options,
+            threads: std::thread::available_parallelism().unwrap_or(NonZeroUsize::MIN),
+        }
+    }
+
+    pub fn with_threads(mut self, threads: NonZeroUsize) -> Self {
+        self.threads = threads;
+        self
+    }
+
+    pub fn options(&self) -> &LexerOptions {
+        &self.options
+    }
+
+    pub fn threads(&self) -> NonZeroUsize {
+        self.threads
+    }
+
+    /// Reads and decodes the files on the thread pool, and adds the ones
+    /// that could be read to the source map.
+    pub fn load<P>(&self, source_map: &mut SourceMap, paths: &[P], charset: InputCharset) -> Vec<io::Result<FileId>>
+            where P: AsRef<Path> + Sync {
+        let files = parallel_map(paths, self.threads, |path| {
+            let path = path.as_ref();
+            let bytes = std::fs::read(path)?;
+            Ok(SourceFile::decode(path.to_path_buf(), &bytes, charset))
+        });
+
+        files.into_iter()
+            .map(|file| file.map(|file| source_map.add_source_file(file)))
+            .collect()
+    }
+
+    /// Lexes the files of the source map on the thread pool. The diagnostics
+    /// of every file are sorted by their position.
+    pub fn lex<'source_map>(&self, source_map: &'source_map SourceMap, files: &[FileId]) -> Vec<LexedFile<'source_map>> {
+        parallel_map(files, self.threads, |file| {
+            let mut lexer = source_map.lexer(*file, self.options.clone());
+            let tokens = lexer.by_ref().collect();
+
+            let mut diagnostics = lexer.take_diagnostics();
+            diagnostics.sort_by_key(|diagnostic| diagnostic.span.start());
+
+            LexedFile {
+                file: *file,
+                tokens,
+                diagnostics,
+            }
+        })
+    }
+}
+
+/// Applies the function to every item on at most the given number of scoped
+/// threads, keeping the order of the items.
+fn parallel_map<T, U, F>(items: &[T], threads: NonZeroUsize, function: F) -> Vec<U>
+        where T: Sync, U: Send, F: Fn(&T) -> U + Sync {
+    let next_index = AtomicUsize::new(0);
+    let work = || {
+        let mut results = Vec::new();
+        loop {
+            let index = next_index.fetch_add(1, Ordering::Relaxed);
+            let Some(item) = items.get(index) else {
+                return results;
+            };
+
+            results.push((index, function(item)));
+        }
+    };
+
+    let mut results: Vec<Option<U>> = std::iter::repeat_with(|| None).take(items.len()).collect();
+    std::thread::scope(|scope| {
+        let workers: Vec<_> = (0..threads.get().min(items.len()))
+            .map(|_| scope.spawn(work))
+            .collect();
+
+        for worker in workers {
+            let worker_results = worker.join().unwrap_or_else(|panic| std::panic::resume_unwind(panic));
+            for (index, result) in worker_results {
+                results[index] = Some(result);
+            }
+        }
+    });
+
+    results.into_iter()
+        .map(|result| result.expect("every item is handled by a worker"))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use rstest::rstest;
+    use super::*;
+    use pretty_assertions::assert_eq;
+    use crate::{
+        DiagnosticKind,
+        Span,
+    };
+
+    #[rstest]
+    fn same_as_lexing_one_by_one(#[values(1, 2, 8)] threads: usize) {
+        let mut source_map = SourceMap::new();
+        let files: Vec<_> = (0..20)
+            .map(|index| source_map.add_file(format!("{index}.h"), format!("int f{index}(void);\nchar *s = \"{index}")))
+            .collect();
+
+        let lexer = ParallelLexer::new(LexerOptions::default())
+            .with_threads(NonZeroUsize::new(threads).unwrap());
+        let lexed = lexer.lex(&source_map, &files);
+
+        let expected: Vec<_> = files.iter()
+            .map(|file| {
+                let mut lexer = source_map.lexer(*file, LexerOptions::default());
+                LexedFile {
+                    file: *file,
+                    tokens: lexer.by_ref().collect(),
+                    diagnostics: lexer.take_diagnostics(),
+                }
+            })
+            .collect();
+
+        assert_eq!(lexed, expected);
+        assert_eq!(lexed[3].diagnostics[0].kind, DiagnosticKind::UnterminatedStringLiteral);
+    }
+
+    #[test]
+    fn load_keeps_order() {
+        let directory = std::env::temp_dir().join(format!("c-lexer-parallel-{}", std::process::id()));
+        std::fs::create_dir_all(&directory).unwrap();
+
+        let paths: Vec<_> = (0..5).map(|index| directory.join(format!("{index}.c"))).collect();
+        for (index, path) in paths.iter().enumerate().skip(1) {
+            std::fs::write(path, [format!("int x{index};").as_bytes(), b"\xFF"].concat()).unwrap();
+        }
+
+        let mut source_map = SourceMap::new();
+        let lexer = ParallelLexer::new(LexerOptions::default())
+            .with_threads(NonZeroUsize::new(3).unwrap());
+        let files = lexer.load(&mut source_map, &paths, InputCharset::Utf8);
+        std::fs::remove_dir_all(&directory).unwrap();
+
+        assert_eq!(files[0].as_ref().unwrap_err().kind(), io::ErrorKind::NotFound);
+
+        let files: Vec<_> = files.into_iter().skip(1).map(Result::unwrap).collect();
+        for (index, file) in files.iter().enumerate() {
+            assert_eq!(source_map.file(*file).path(), paths[index + 1]);
+        }
+
+        let lexed = lexer.lex(&source_map, &files);
+        assert_eq!(lexed[1].tokens[1].to_string(), "x2");
+        assert_eq!(lexed[1].diagnostics[0].span, Span::new(files[1], 7, 10));
+    }
+}
@@ -0,0 +1,206 @@
+// Copyright (C) 2023 Tristan Gerritsen <tristan@thewoosh.org>
+// All Rights Reserved.
+
+use std::collections::{
+    HashMap,
+    HashSet,
+};
+
+use crate::{
+    Diagnostic,
+    DiagnosticKind,
+    PreprocessorToken,
+    PreprocessorTokenKind,
+    Symbol,
+};
+
+use super::Lexer;
+
+/// The skeletons (Unicode UTS #39 section 4) of the identifiers lexed so far,
+/// to find identifiers that look alike.
+#[derive(Clone, Debug, Default)]
+pub(super) struct IdentifierSkeletons {
+    seen: HashSet<Symbol>,
+    skeletons: HashMap<String, Symbol>,
+
+    /// The symbols in the order they were first seen, so that the identifiers
+    /// of text that is lexed again can be forgotten.
+    order: Vec<Symbol>,
+}
+
+impl IdentifierSkeletons {
+    pub(super) fn len(&self) -> usize {
+        self.order.len()
+    }
+
+    /// Forgets the identifiers that were first seen after the first `len`.
+    pub(super) fn truncate(&mut self, len: usize) {
+        for symbol in self.order.drain(len..) {
+            self.seen.remove(&symbol);
+
+            let skeleton = skeleton(symbol.as_str());
+            if self.skeletons.get(&skeleton) == Some(&symbol) {
+                self.skeletons.remove(&skeleton);
+            }
+        }
+    }
+}
+
+fn skeleton(identifier: &str) -> String {
+    unicode_security::skeleton(identifier).collect()
+}
+
+/// Whether the character is one of the explicit directional formatting
+/// characters of Unicode UAX #9, as listed by CVE-2021-42574.
+fn is_bidirectional_control_character(char: char) -> bool {
+    matches!(char, '\u{061C}' | '\u{200E}' | '\u{200F}' | '\u{202A}'..='\u{202E}' | '\u{2066}'..='\u{2069}')
+}
+
+impl<'source_code> Lexer<'source_code> {
+    /// Reports the bidirectional control characters in comments and literals.
+    /// Elsewhere, they are already reported as stray characters.
+    pub(super) fn check_bidirectional_control_characters(&mut self, token: &PreprocessorToken<'source_code>) {
+        if !matches!(token.kind, PreprocessorTokenKind::Comment(..) | PreprocessorTokenKind::CharacterConstant(..)
+                | PreprocessorTokenKind::StringLiteral(..)) {
+            return;
+        }
+
+        let start = token.span.start();
+        let text = &self.source_code.as_str()[token.span.range()];
+        if text.is_ascii() {
+            return;
+        }
+
+        for (index, char) in text.char_indices() {
+            if is_bidirectional_control_character(char) {
+                self.diagnostics.push(Diagnostic {
+                    kind: DiagnosticKind::BidirectionalControlCharacter(char),
+                    span: self.span(start + index, start + index + char.len_utf8()),
+                });
+            }
+        }
+    }
+
+    /// Reports the identifier if it has the same skeleton as an identifier
+    /// before it, unless both are ASCII. Every identifier is only checked the
+    /// first time it appears.
+    pub(super) fn check_confusable_identifier(&mut self, symbol: Symbol, start: usize, end: usize) {
+        let skeletons = &mut self.identifier_skeletons;
+        if !skeletons.seen.insert(symbol) {
+            return;
+        }
+
+        skeletons.order.push(symbol);
+
+        let identifier = symbol.as_str();
+        let other = *skeletons.skeletons.entry(skeleton(identifier)).or_insert(symbol);
+        if other != symbol && !(identifier.is_ascii() && other.as_str().is_ascii()) {
+            self.diagnostics.push(Diagnostic {
+                kind: DiagnosticKind::ConfusableIdentifier {
+                    identifier: symbol,
+                    other,
+                },
+                span: self.span(start, end),
+            });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rstest::rstest;
+    use super::*;
+    use pretty_assertions::assert_eq;
+    use crate::{
+        LexerOptions,
+        SecurityChecks,
+        Span,
+    };
+
+    fn diagnostics(source_code: &str, security: SecurityChecks) -> Vec<Diagnostic> {
+        let mut lexer = Lexer::with_options(source_code, LexerOptions {
+            security,
+            ..Default::default()
+        });
+
+        lexer.by_ref().for_each(drop);
+        lexer.take_diagnostics()
+    }
+
+    fn bidirectional_control_character(char: char, start: usize) -> Diagnostic {
+        Diagnostic {
+            kind: DiagnosticKind::BidirectionalControlCharacter(char),
+            span: Span::detached(start, start + char.len_utf8()),
+        }
+    }
+
+    fn confusable_identifier(identifier: &str, other: &str, start: usize) -> Diagnostic {
+        Diagnostic {
+            kind: DiagnosticKind::ConfusableIdentifier {
+                identifier: Symbol::intern(identifier),
+                other: Symbol::intern(other),
+            },
+            span: Span::detached(start, start + identifier.len()),
+        }
+    }
+
+    #[rstest]
+    #[case("/* \u{202E} } \u{2066} */", vec![
+        bidirectional_control_character('\u{202E}', 3),
+        bidirectional_control_character('\u{2066}', 9),
+    ])]
+    #[case("// \u{200F}\nx", vec![bidirectional_control_character('\u{200F}', 3)])]
+    #[case("s = \"user\u{202E} \u{2066}// Check\";", vec![
+        bidirectional_control_character('\u{202E}', 9),
+        bidirectional_control_character('\u{2066}', 13),
+    ])]
+    #[case("c = L'\u{061C}';", vec![bidirectional_control_character('\u{061C}', 6)])]
+    #[case("/* ש */ \"é\"", vec![])]
+    fn bidirectional_control_characters(#[case] source_code: &str, #[case] expected: Vec<Diagnostic>) {
+        assert_eq!(diagnostics(source_code, SecurityChecks::ALL), expected);
+    }
+
+    #[rstest]
+    #[case("int a; int а;", vec![confusable_identifier("а", "a", 11)])]
+    #[case("Ѕcope x; Scope y; Ѕcope z;", vec![confusable_identifier("Scope", "Ѕcope", 10)])]
+    #[case("int l1, ll, Il;", vec![])]
+    #[case("int é, é2;", vec![])]
+    #[case("int rn; int m;", vec![])]
+    fn confusable_identifiers(#[case] source_code: &str, #[case] expected: Vec<Diagnostic>) {
+        assert_eq!(diagnostics(source_code, SecurityChecks::ALL), expected);
+    }
+
+    #[test]
+    fn disabled_by_default() {
+        assert_eq!(diagnostics("int a; int а; // \u{202E}", SecurityChecks::default()), vec![]);
+    }
+
+    #[test]
+    fn opt_out_of_single_diagnostic() {
+        let source_code = "int a; int а; // \u{202E}";
+
+        assert_eq!(diagnostics(source_code, SecurityChecks {
+            confusable_identifiers: false,
+            ..SecurityChecks::ALL
+        }), vec![bidirectional_control_character('\u{202E}', 18)]);
+
+        assert_eq!(diagnostics(source_code, SecurityChecks {
+            bidirectional_control_characters: false,
+            ..SecurityChecks::ALL
+        }), vec![confusable_identifier("а", "a", 11)]);
+    }
+
+    #[test]
+    fn forget_identifiers() {
+        let mut lexer = Lexer::new("");
+
+        lexer.check_confusable_identifier(Symbol::intern("a"), 0, 1);
+        let len = lexer.identifier_skeletons.len();
+        lexer.check_confusable_identifier(Symbol::intern("b"), 0, 1);
+        lexer.identifier_skeletons.truncate(len);
+
+        assert_eq!(lexer.identifier_skeletons.order, vec![Symbol::intern("a")]);
+        assert!(!lexer.identifier_skeletons.skeletons.contains_key("b"));
+        assert!(!lexer.identifier_skeletons.seen.contains(&Symbol::intern("b")));
+    }
+}
@@ -1,57 +1,234 @@
 // Copyright (C) 2023 Tristan Gerritsen <tristan@thewoosh.org>
 // All Rights Reserved.
 
-use std::str::CharIndices;
-
 /// Helps orchestrate the intake of characters by the [`Lexer`][crate::Lexer],
 /// whilst providing a peekable interface.
-#[derive(Clone, Debug)]
+///
+/// When enabled, trigraph sequences are replaced by the character they
+/// represent, as part of translation phase 1 (ISO/IEC 9899 5.1.1.2). Line
+/// splices (a backslash immediately followed by a newline) are skipped over
+/// as part of translation phase 2, so the lexer never sees them. The indices
+/// still refer to the original text, where a splice is counted as part of the
+/// character that follows it.
+///
+/// The text is scanned as bytes: ASCII characters, which make up nearly all of
+/// C source code, are handled without UTF-8 decoding, and runs of characters
+/// the lexer isn't interested in can be skipped in bulk.
+#[derive(Debug)]
 pub(super) struct SourceCode<'source_code> {
     str: &'source_code str,
-    data: CharIndices<'source_code>,
-    previous: Option<(usize, char)>,
-    current: Option<(usize, char)>,
-    peeked_char: Option<(usize, char)>,
+    trigraphs: bool,
+    position: Position,
 }
 
-impl<'source_code> SourceCode<'source_code> {
+/// A saved position of a [`SourceCode`], to backtrack to after looking ahead.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub(super) struct Position {
+    /// The byte offset of the peeked character, including the line splices
+    /// before it.
+    index: usize,
+
+    /// The peeked character and the amount of bytes it spans in `str`,
+    /// including the line splices before it.
+    peeked_char: Option<(char, usize)>,
+}
+
+impl Position {
     pub const fn index(&self) -> usize {
-        if let Some((index, _)) = self.peeked_char {
-            return index;
+        self.index
+    }
+}
+
+/// A set of ASCII bytes, which can be tested for membership with a single
+/// lookup.
+pub(super) struct ByteSet([bool; 256]);
+
+impl ByteSet {
+    pub const fn new(bytes: &[u8]) -> Self {
+        let mut set = [false; 256];
+
+        let mut index = 0;
+        while index < bytes.len() {
+            assert!(bytes[index].is_ascii());
+            set[bytes[index] as usize] = true;
+            index += 1;
         }
 
-        if let Some((index, char)) = self.current {
-            return index + char.len_utf8();
+        Self(set)
+    }
+
+    /// The set of the bytes in the ranges, e.g. `[(b'a', b'z')]`.
+    pub const fn from_ranges(ranges: &[(u8, u8)]) -> Self {
+        let mut set = [false; 256];
+
+        let mut index = 0;
+        while index < ranges.len() {
+            let (mut byte, last) = ranges[index];
+            assert!(last.is_ascii());
+
+            while byte <= last {
+                set[byte as usize] = true;
+                byte += 1;
+            }
+
+            index += 1;
         }
 
-        let Some((previous_index, previous_char)) = self.previous else {
-            debug_assert!(self.str.is_empty());
-            return 0;
+        Self(set)
+    }
+
+    pub const fn contains(&self, byte: u8) -> bool {
+        self.0[byte as usize]
+    }
+}
+
+impl<'source_code> SourceCode<'source_code> {
+    pub fn new(str: &'source_code str, trigraphs: bool) -> Self {
+        let mut instance = Self {
+            str,
+            trigraphs,
+            position: Position {
+                index: 0,
+                peeked_char: None,
+            },
         };
 
-        previous_index + previous_char.len_utf8()
+        instance.position.peeked_char = instance.char_at(0);
+        instance
+    }
+
+    pub const fn index(&self) -> usize {
+        // Line splices at the end of the file belong to the last character.
+        if self.position.peeked_char.is_none() {
+            return self.str.len();
+        }
+
+        self.position.index
     }
 
     pub const fn as_str(&self) -> &'source_code str {
         self.str
     }
 
+    /// The part of the source code that hasn't been consumed yet.
+    pub fn remaining(&self) -> &'source_code str {
+        &self.str[self.index()..]
+    }
+
+    pub const fn position(&self) -> Position {
+        self.position
+    }
+
+    /// Backtracks (or skips ahead) to a position that was previously saved
+    /// with [`SourceCode::position`].
+    pub fn restore(&mut self, position: Position) {
+        self.position = position;
+    }
+
     pub fn peek(&self) -> Option<char> {
-        self.peeked_char.map(|tuple| tuple.1)
+        self.position.peeked_char.map(|tuple| tuple.0)
+    }
+
+    /// The character after the peeked one.
+    pub fn peek_second(&self) -> Option<char> {
+        let (_, length) = self.position.peeked_char?;
+        self.char_at(self.position.index + length).map(|tuple| tuple.0)
+    }
+
+    /// Consumes the ASCII characters in the set, and returns their text. The
+    /// set must not contain `\\` or `?`, as those might start a line splice or
+    /// trigraph, which aren't skipped over.
+    pub fn consume_ascii_while(&mut self, set: &ByteSet) -> &'source_code str {
+        debug_assert!(!set.contains(b'\\') && !set.contains(b'?'));
+
+        let start = self.position.index;
+        let length = self.str.as_bytes()[start..]
+            .iter()
+            .position(|byte| !set.contains(*byte))
+            .unwrap_or(self.str.len() - start);
+
+        self.seek(start + length);
+        &self.str[start..start + length]
+    }
+
+    /// Skips over the characters up until the first character in the set
+    /// (which must be ASCII), a line splice or a trigraph, whichever comes
+    /// first. This is meant for text of which the characters themselves don't
+    /// matter, e.g. the contents of comments.
+    pub fn skip_until(&mut self, set: &ByteSet) {
+        let start = self.position.index;
+        let length = self.str.as_bytes()[start..]
+            .iter()
+            .position(|byte| set.contains(*byte) || *byte == b'\\' || (self.trigraphs && *byte == b'?'))
+            .unwrap_or(self.str.len() - start);
+
+        if length != 0 {
+            self.seek(start + length);
+        }
+    }
+
+    pub fn skip_to_end(&mut self) {
+        self.seek(self.str.len());
+    }
+
+    /// Continues at the byte offset, which must be at the start of a
+    /// character, line splice or trigraph.
+    pub fn seek(&mut self, index: usize) {
+        self.position = Position {
+            index,
+            peeked_char: self.char_at(index),
+        };
+    }
+
+    fn char_at(&self, index: usize) -> Option<(char, usize)> {
+        // Fast path for the common case of an ASCII character that can't be
+        // the start of a line splice or trigraph.
+        let byte = *self.str.as_bytes().get(index)?;
+        if byte.is_ascii() && byte != b'\\' && byte != b'?' {
+            return Some((byte as char, 1));
+        }
+
+        let mut position = index;
+
+        loop {
+            let (char, length) = self.physical_char_at(position)?;
+            if char != '\\' {
+                return Some((char, position + length - index));
+            }
+
+            match self.str.as_bytes().get(position + length) {
+                Some(b'\n') => position += length + 1,
+                Some(b'\r') if self.str.as_bytes().get(position + length + 1) == Some(&b'\n') => {
+                    position += length + 2;
+                }
+                _ => return Some((char, position + length - index)),
+            }
+        }
+    }
+
+    /// The character at the index after translation phase 1.
+    fn physical_char_at(&self, index: usize) -> Option<(char, usize)> {
+        let remaining = &self.str[index..];
+
+        if self.trigraphs {
+            if let Some(char) = trigraph_at_start_of(remaining) {
+                return Some((char, 3));
+            }
+        }
+
+        let byte = *remaining.as_bytes().first()?;
+        if byte.is_ascii() {
+            return Some((byte as char, 1));
+        }
+
+        let char = remaining.chars().next()?;
+        Some((char, char.len_utf8()))
     }
 }
 
 impl<'source_code> From<&'source_code str> for SourceCode<'source_code> {
     fn from(value: &'source_code str) -> Self {
-        let mut iter = value.char_indices();
-        let peeked_char = iter.next();
-        Self {
-            str: value,
-            data: iter,
-            current: None,
-            peeked_char,
-            previous: None,
-        }
+        Self::new(value, false)
     }
 }
 
@@ -59,20 +236,34 @@ impl<'source_code> Iterator for SourceCode<'source_code> {
     type Item = char;
 
     fn next(&mut self) -> Option<Self::Item> {
-        let peeked = self.peeked_char
-            .or_else(|| self.data.next());
+        let (char, length) = self.position.peeked_char?;
+        self.seek(self.position.index + length);
+        Some(char)
+    }
+}
 
-        self.previous = self.current;
-        self.current = peeked;
-        self.peeked_char = self.data.next();
+/// The character the trigraph sequence (ISO/IEC 9899 5.2.1.1) at the start of
+/// `str` is replaced with, if there is one.
+pub(super) fn trigraph_at_start_of(str: &str) -> Option<char> {
+    let replacement = match str.strip_prefix("??")?.chars().next()? {
+        '=' => '#',
+        '(' => '[',
+        '/' => '\\',
+        ')' => ']',
+        '\'' => '^',
+        '<' => '{',
+        '!' => '|',
+        '>' => '}',
+        '-' => '~',
+        _ => return None,
+    };
 
-        let (_, current) = self.current?;
-        Some(current)
-    }
+    Some(replacement)
 }
 
 #[cfg(test)]
 mod tests {
+    use rstest::rstest;
     use super::*;
 
     #[test]
@@ -119,4 +310,110 @@ mod tests {
 
         assert_eq!(instance.next(), None);
     }
+
+    #[test]
+    fn trigraphs() {
+        let mut instance = SourceCode::new("a??=b??", true);
+        assert_eq!(instance.next(), Some('a'));
+        assert_eq!(instance.index(), 1);
+        assert_eq!(instance.peek(), Some('#'));
+
+        assert_eq!(instance.next(), Some('#'));
+        assert_eq!(instance.index(), 4);
+
+        assert_eq!(instance.next(), Some('b'));
+        assert_eq!(instance.next(), Some('?'));
+        assert_eq!(instance.next(), Some('?'));
+        assert_eq!(instance.next(), None);
+        assert_eq!(instance.index(), 7);
+    }
+
+    #[rstest]
+    #[case("a\\\nb", "ab")]
+    #[case("a\\\r\nb", "ab")]
+    #[case("\\\n\\\na", "a")]
+    #[case("a\\", "a\\")]
+    #[case("a\\ \nb", "a\\ \nb")]
+    #[case("a\\\rb", "a\\\rb")]
+    #[case("\\\n", "")]
+    fn line_splices(#[case] input: &str, #[case] expected: &str) {
+        let actual: String = SourceCode::from(input).collect();
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn line_splice_indices() {
+        let mut instance = SourceCode::from("a\\\nb");
+        assert_eq!(instance.next(), Some('a'));
+        assert_eq!(instance.index(), 1);
+        assert_eq!(instance.peek(), Some('b'));
+        assert_eq!(instance.next(), Some('b'));
+        assert_eq!(instance.index(), 4);
+        assert_eq!(instance.next(), None);
+    }
+
+    #[test]
+    fn line_splice_at_end_of_file() {
+        let mut instance = SourceCode::from("a\\\n");
+        assert_eq!(instance.next(), Some('a'));
+        assert_eq!(instance.peek(), None);
+        assert_eq!(instance.index(), 3);
+    }
+
+    #[test]
+    fn trigraph_line_splice() {
+        let actual: String = SourceCode::new("a??/\nb", true).collect();
+        assert_eq!(actual, "ab");
+    }
+
+    #[test]
+    fn trigraphs_disabled() {
+        let instance: String = SourceCode::new("??=", false).collect();
+        assert_eq!(instance, "??=");
+    }
+
+    #[test]
+    fn position_restore() {
+        let mut instance = SourceCode::from("ab\\\nc");
+        _ = instance.next();
+        let position = instance.position();
+
+        assert_eq!(instance.next(), Some('b'));
+        assert_eq!(instance.next(), Some('c'));
+        instance.restore(position);
+
+        assert_eq!(instance.index(), 1);
+        assert_eq!(instance.peek(), Some('b'));
+        assert_eq!(instance.peek_second(), Some('c'));
+    }
+
+    #[rstest]
+    #[case("abc def", "abc", Some(' '))]
+    #[case("abc", "abc", None)]
+    #[case("ab\\\nc", "ab", Some('c'))]
+    #[case("aé", "a", Some('é'))]
+    #[case(" a", "", Some(' '))]
+    fn consume_ascii_while(#[case] input: &str, #[case] expected: &str, #[case] peek: Option<char>) {
+        const LOWERCASE: ByteSet = ByteSet::from_ranges(&[(b'a', b'z')]);
+
+        let mut instance = SourceCode::from(input);
+        assert_eq!(instance.consume_ascii_while(&LOWERCASE), expected);
+        assert_eq!(instance.peek(), peek);
+    }
+
+    #[rstest]
+    #[case("comment */", false, Some('*'), 8)]
+    #[case("comment", false, None, 7)]
+    #[case("é😀 */", false, Some('*'), 7)]
+    #[case("a\\\n*", false, Some('*'), 1)]
+    #[case("a?? */", false, Some('*'), 4)]
+    #[case("a??/\n*", true, Some('*'), 1)]
+    fn skip_until(#[case] input: &str, #[case] trigraphs: bool, #[case] peek: Option<char>, #[case] index: usize) {
+        const STAR: ByteSet = ByteSet::new(b"*");
+
+        let mut instance = SourceCode::new(input, trigraphs);
+        instance.skip_until(&STAR);
+        assert_eq!(instance.index(), index);
+        assert_eq!(instance.peek(), peek);
+    }
 }
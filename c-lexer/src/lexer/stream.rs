@@ -0,0 +1,210 @@
+// Copyright (C) 2023 Tristan Gerritsen <tristan@thewoosh.org>
+// All Rights Reserved.
+
+use crate::{
+    Diagnostic,
+    Token,
+};
+
+use super::Lexer;
+
+/// The [`Token`]s of a [`Lexer`] for a parser, with arbitrary lookahead and
+/// backtracking. Whitespace and comments are skipped, and the problems of
+/// tokens that can't be converted are reported as diagnostics.
+///
+/// Tokens are lexed on demand and kept, so that the stream can be rewound to
+/// any [`Checkpoint`].
+pub struct TokenStream<'source_code> {
+    lexer: Lexer<'source_code>,
+    tokens: Vec<Token>,
+    position: usize,
+    diagnostics: Vec<Diagnostic>,
+}
+
+/// A position in a [`TokenStream`] to [rewind][TokenStream::rewind] to.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Checkpoint(usize);
+
+impl<'source_code> TokenStream<'source_code> {
+    pub fn new(lexer: Lexer<'source_code>) -> Self {
+        Self {
+            lexer,
+            tokens: Vec::new(),
+            position: 0,
+            diagnostics: Vec::new(),
+        }
+    }
+
+    /// The token [`Iterator::next`] returns, without consuming it.
+    pub fn peek(&mut self) -> Option<&Token> {
+        self.peek_nth(0)
+    }
+
+    /// The token `n` tokens ahead, where `peek_nth(0)` is the next token.
+    pub fn peek_nth(&mut self, n: usize) -> Option<&Token> {
+        while self.tokens.len() <= self.position + n {
+            let token = self.lex_token()?;
+            self.tokens.push(token);
+        }
+
+        self.tokens.get(self.position + n)
+    }
+
+    /// Consumes the next token if it matches the predicate.
+    pub fn next_if(&mut self, predicate: impl FnOnce(&Token) -> bool) -> Option<Token> {
+        if self.peek().is_some_and(predicate) {
+            return self.next();
+        }
+
+        None
+    }
+
+    /// The current position, to backtrack to after parsing speculatively.
+    pub fn checkpoint(&self) -> Checkpoint {
+        Checkpoint(self.position)
+    }
+
+    /// Continues at a checkpoint of this stream, so the tokens after it are
+    /// returned again.
+    pub fn rewind(&mut self, checkpoint: Checkpoint) {
+        debug_assert!(checkpoint.0 <= self.tokens.len(), "checkpoint of another stream");
+        self.position = checkpoint.0;
+    }
+
+    pub fn lexer(&self) -> &Lexer<'source_code> {
+        &self.lexer
+    }
+
+    /// Takes the diagnostics of the lexer and of the conversion of the tokens
+    /// lexed so far. Peeking or rewinding doesn't report them again.
+    pub fn take_diagnostics(&mut self) -> Vec<Diagnostic> {
+        let mut diagnostics = self.lexer.take_diagnostics();
+        diagnostics.append(&mut self.diagnostics);
+        diagnostics.sort_by_key(|diagnostic| diagnostic.span.start());
+        diagnostics
+    }
+
+    fn lex_token(&mut self) -> Option<Token> {
+        loop {
+            let token = self.lexer.next()?;
+            let span = token.span;
+
+            match token.into_token(&self.lexer.options) {
+                Ok(token) => return Some(token),
+                Err(error) => self.diagnostics.extend(error.to_diagnostic(span)),
+            }
+        }
+    }
+}
+
+impl<'source_code> From<Lexer<'source_code>> for TokenStream<'source_code> {
+    fn from(value: Lexer<'source_code>) -> Self {
+        Self::new(value)
+    }
+}
+
+impl Iterator for TokenStream<'_> {
+    type Item = Token;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let token = self.peek()?.clone();
+        self.position += 1;
+        Some(token)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rstest::rstest;
+    use super::*;
+    use pretty_assertions::assert_eq;
+    use crate::{
+        DiagnosticKind,
+        Keyword,
+        LexerOptions,
+        Punctuator,
+        Span,
+        TokenKind,
+    };
+
+    fn stream(source_code: &str) -> TokenStream<'_> {
+        TokenStream::new(Lexer::new(source_code))
+    }
+
+    fn punctuator(punctuator: Punctuator) -> TokenKind {
+        TokenKind::Punctuator(punctuator)
+    }
+
+    #[rstest]
+    #[case(false)]
+    #[case(true)]
+    fn skips_trivia(#[case] trivia: bool) {
+        let lexer = Lexer::with_options("int /* a */ x;\n", LexerOptions {
+            trivia,
+            ..Default::default()
+        });
+
+        let kinds: Vec<_> = TokenStream::new(lexer).map(|token| token.kind).collect();
+        assert_eq!(kinds, [
+            TokenKind::Keyword(Keyword::Int),
+            TokenKind::Identifier("x".into()),
+            punctuator(Punctuator::Semicolon),
+        ]);
+    }
+
+    #[test]
+    fn peek_nth() {
+        let mut stream = stream("a + b");
+        assert_eq!(stream.peek_nth(2).map(|token| &token.kind), Some(&TokenKind::Identifier("b".into())));
+        assert_eq!(stream.peek_nth(3), None);
+        assert_eq!(stream.peek().map(|token| &token.kind), Some(&TokenKind::Identifier("a".into())));
+
+        assert_eq!(stream.next().map(|token| token.kind), Some(TokenKind::Identifier("a".into())));
+        assert_eq!(stream.peek_nth(1).map(|token| token.span), Some(Span::detached(4, 5)));
+    }
+
+    #[test]
+    fn next_if() {
+        let mut stream = stream("( x");
+        assert_eq!(stream.next_if(|token| token.kind == punctuator(Punctuator::Semicolon)), None);
+        assert!(stream.next_if(|token| token.kind == punctuator(Punctuator::LeftParenthesis)).is_some());
+        assert_eq!(stream.next().map(|token| token.kind), Some(TokenKind::Identifier("x".into())));
+        assert_eq!(stream.next_if(|_| true), None);
+    }
+
+    /// Speculatively parses `(T)(x)` as a cast, and backtracks when `T`
+    /// turns out not to be a type name.
+    #[test]
+    fn rewind() {
+        let mut stream = stream("(T)(x)");
+        let checkpoint = stream.checkpoint();
+
+        assert_eq!(stream.next().map(|token| token.kind), Some(punctuator(Punctuator::LeftParenthesis)));
+        assert_eq!(stream.next().map(|token| token.kind), Some(TokenKind::Identifier("T".into())));
+        assert_eq!(stream.next().map(|token| token.kind), Some(punctuator(Punctuator::RightParenthesis)));
+
+        stream.rewind(checkpoint);
+        let kinds: Vec<_> = stream.map(|token| token.kind).collect();
+        assert_eq!(kinds.len(), 6);
+        assert_eq!(kinds[1], TokenKind::Identifier("T".into()));
+    }
+
+    #[test]
+    fn conversion_problems_are_reported_once() {
+        let mut stream = stream("a @ 0x");
+        let checkpoint = stream.checkpoint();
+        assert_eq!(stream.by_ref().count(), 1);
+
+        stream.rewind(checkpoint);
+        assert_eq!(stream.by_ref().count(), 1);
+
+        let kinds: Vec<_> = stream.take_diagnostics()
+            .into_iter()
+            .map(|diagnostic| diagnostic.kind)
+            .collect();
+        assert_eq!(kinds, [
+            DiagnosticKind::StrayCharacter('@'),
+            DiagnosticKind::InvalidConstant(crate::ConstantError::MissingDigits),
+        ]);
+    }
+}
@@ -0,0 +1,487 @@
+// Copyright (C) 2023 Tristan Gerritsen <tristan@thewoosh.org>
+// All Rights Reserved.
+
+use std::{
+    collections::VecDeque,
+    io::{
+        self,
+        Read,
+    },
+};
+
+use crate::{
+    encoding::Decoder,
+    Diagnostic,
+    FileId,
+    InputCharset,
+    LexerOptions,
+    PreprocessorToken,
+    PreprocessorTokenKind,
+    Span,
+    TokenFlags,
+};
+
+use super::{
+    header_name::DirectiveState,
+    security::IdentifierSkeletons,
+    Lexer,
+};
+
+/// The number of bytes read at once, unless a single line doesn't fit.
+const DEFAULT_CHUNK_SIZE: usize = 64 * 1024;
+
+/// A [`Lexer`] that reads UTF-8 source code from an [`io::Read`] in chunks,
+/// so the input never has to be in memory as a whole. It produces the same
+/// tokens and diagnostics as a [`Lexer`] over the whole text, with owned
+/// payloads and spans relative to the start of the input.
+///
+/// Only complete lines are lexed: no token or lookahead continues past a line
+/// break that isn't part of a line splice, except for whitespace, comments
+/// and raw string literals, which then end after it. The text after the last
+/// such line break is kept until the next chunk is read, so tokens, comments,
+/// splices and characters straddling chunks are lexed as a whole.
+///
+/// Like [`DecodedSource`][crate::DecodedSource], a byte order mark is
+/// skipped, invalid UTF-8 is replaced by U+FFFD and CRLF and CR line endings
+/// are normalized to LF, so the spans are offsets in the decoded text. They
+/// can be mapped back to the bytes with [`StreamingLexer::original_span`].
+pub struct StreamingLexer<R> {
+    reader: R,
+    file: FileId,
+    options: LexerOptions,
+
+    /// The decoded text that hasn't been turned into tokens yet.
+    text: String,
+
+    /// The offset of `text` in the decoded input.
+    offset: usize,
+
+    /// The bytes at the end of the last chunk that don't form a complete
+    /// character yet.
+    undecoded: Vec<u8>,
+
+    /// The decoder of the input, which is created once it's known whether
+    /// the input starts with a byte order mark.
+    decoder: Option<Decoder>,
+
+    read_buffer: Vec<u8>,
+    chunk_size: usize,
+    end_of_input: bool,
+
+    /// The state of the lexer at the start of `text`.
+    next_flags: TokenFlags,
+    directive_state: DirectiveState,
+    identifier_skeletons: IdentifierSkeletons,
+
+    tokens: VecDeque<PreprocessorToken<'static>>,
+    diagnostics: Vec<Diagnostic>,
+}
+
+impl<R: Read> StreamingLexer<R> {
+    pub fn new(reader: R, options: LexerOptions) -> Self {
+        Self::with_file(reader, FileId::DETACHED, options)
+    }
+
+    /// Creates a streaming lexer of which the tokens refer to the given file.
+    pub fn with_file(reader: R, file: FileId, options: LexerOptions) -> Self {
+        Self {
+            reader,
+            file,
+            options,
+            text: String::new(),
+            offset: 0,
+            undecoded: Vec::new(),
+            decoder: None,
+            read_buffer: Vec::new(),
+            chunk_size: DEFAULT_CHUNK_SIZE,
+            end_of_input: false,
+            next_flags: TokenFlags::START_OF_FILE,
+            directive_state: DirectiveState::default(),
+            identifier_skeletons: IdentifierSkeletons::default(),
+            tokens: VecDeque::new(),
+            diagnostics: Vec::new(),
+        }
+    }
+
+    /// Reads chunks of the given number of bytes, instead of 64 KiB.
+    pub fn with_chunk_size(mut self, chunk_size: usize) -> Self {
+        assert_ne!(chunk_size, 0, "chunks must contain at least one byte");
+        self.chunk_size = chunk_size;
+        self
+    }
+
+    pub fn options(&self) -> &LexerOptions {
+        &self.options
+    }
+
+    /// The diagnostics that were reported for the tokens produced so far.
+    pub fn diagnostics(&self) -> &[Diagnostic] {
+        &self.diagnostics
+    }
+
+    /// Takes the diagnostics that were reported so far, so that consumers
+    /// can handle them whilst lexing.
+    pub fn take_diagnostics(&mut self) -> Vec<Diagnostic> {
+        std::mem::take(&mut self.diagnostics)
+    }
+
+    /// The byte offset in the input of the offset in the decoded text, for
+    /// the text that was read so far.
+    pub fn original_offset(&self, offset: usize) -> usize {
+        match &self.decoder {
+            Some(decoder) => decoder.offsets().original_offset(offset),
+            None => offset,
+        }
+    }
+
+    /// The span in the input of the span in the decoded text, for the text
+    /// that was read so far.
+    pub fn original_span(&self, span: Span) -> Span {
+        match &self.decoder {
+            Some(decoder) => decoder.offsets().original_span(span),
+            None => span,
+        }
+    }
+
+    /// Reads a chunk, which is larger when a line doesn't fit in the last
+    /// one, so that long lines don't have to be lexed over and over again.
+    fn read_chunk(&mut self, size: usize) -> io::Result<()> {
+        self.read_buffer.resize(size, 0);
+
+        let length = loop {
+            match self.reader.read(&mut self.read_buffer) {
+                Ok(length) => break length,
+                Err(error) if error.kind() == io::ErrorKind::Interrupted => continue,
+                Err(error) => return Err(error),
+            }
+        };
+
+        self.end_of_input = length == 0;
+        self.undecoded.extend_from_slice(&self.read_buffer[..length]);
+        self.decode();
+        Ok(())
+    }
+
+    /// Moves the complete characters of `undecoded` to `text`.
+    fn decode(&mut self) {
+        const BOM: &[u8] = b"\xEF\xBB\xBF";
+
+        let decoder = match &mut self.decoder {
+            Some(decoder) => decoder,
+            None if !self.end_of_input && self.undecoded.len() < BOM.len() && BOM.starts_with(&self.undecoded) => return,
+            None => {
+                let bom_length = if self.undecoded.starts_with(BOM) { BOM.len() } else { 0 };
+                self.undecoded.drain(..bom_length);
+                self.decoder.insert(Decoder::new(InputCharset::Utf8, bom_length, 0))
+            }
+        };
+
+        // A character that continues in the next chunk is kept undecoded.
+        let incomplete = match self.undecoded.utf8_chunks().last() {
+            Some(chunk) if !self.end_of_input => match std::str::from_utf8(chunk.invalid()) {
+                Err(error) if error.error_len().is_none() => chunk.invalid().len(),
+                _ => 0,
+            },
+            _ => 0,
+        };
+
+        let complete = self.undecoded.len() - incomplete;
+        decoder.decode_utf8(&self.undecoded[..complete]);
+        self.undecoded.drain(..complete);
+        decoder.drain_text_into(&mut self.text);
+
+        for mut diagnostic in decoder.take_diagnostics() {
+            diagnostic.span.file = self.file;
+            self.diagnostics.push(diagnostic);
+        }
+    }
+
+    /// Lexes the complete lines of `text`, or all of it at the end of the
+    /// input, and returns whether any text was consumed.
+    fn lex_complete_lines(&mut self) -> bool {
+        let limit = match self.end_of_input {
+            true => self.text.len(),
+            false => complete_lines_end(&self.text, self.options.trigraphs),
+        };
+
+        if limit == 0 && !self.end_of_input {
+            return false;
+        }
+
+        // Trivia is lexed as well, so a run of lines without tokens doesn't
+        // have to be kept in memory.
+        let mut lexer = Lexer::with_file(&self.text, self.file, LexerOptions {
+            trivia: true,
+            ..self.options.clone()
+        });
+        lexer.next_flags = self.next_flags;
+        lexer.directive_state = self.directive_state;
+        lexer.identifier_skeletons = std::mem::take(&mut self.identifier_skeletons);
+
+        let mut end = 0;
+        let mut identifier_count = lexer.identifier_skeletons.len();
+        while let Some(mut token) = lexer.next() {
+            if token.span.end() > limit {
+                break;
+            }
+
+            end = token.span.end();
+            self.next_flags = lexer.next_flags;
+            self.directive_state = lexer.directive_state;
+            identifier_count = lexer.identifier_skeletons.len();
+
+            let is_trivia = matches!(token.kind, PreprocessorTokenKind::Whitespace(..) | PreprocessorTokenKind::Comment(..));
+            if !is_trivia || self.options.trivia {
+                token.span = shift(token.span, self.offset);
+                self.tokens.push_back(token.into_owned());
+            }
+        }
+
+        // The identifiers and problems of the text after `end` are seen again
+        // when it's lexed again.
+        self.identifier_skeletons = std::mem::take(&mut lexer.identifier_skeletons);
+        self.identifier_skeletons.truncate(identifier_count);
+
+        for mut diagnostic in lexer.take_diagnostics() {
+            if diagnostic.span.start() < end || self.end_of_input {
+                diagnostic.span = shift(diagnostic.span, self.offset);
+                self.diagnostics.push(diagnostic);
+            }
+        }
+
+        if self.end_of_input {
+            end = self.text.len();
+        }
+
+        self.text.drain(..end);
+        self.offset += end;
+        end != 0
+    }
+}
+
+impl<R: Read> Iterator for StreamingLexer<R> {
+    type Item = io::Result<PreprocessorToken<'static>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let mut read_size = self.chunk_size;
+
+        loop {
+            if let Some(token) = self.tokens.pop_front() {
+                return Some(Ok(token));
+            }
+
+            if self.end_of_input && self.text.is_empty() {
+                return None;
+            }
+
+            if let Err(error) = self.read_chunk(read_size) {
+                return Some(Err(error));
+            }
+
+            if !self.lex_complete_lines() {
+                read_size = read_size.saturating_mul(2);
+            }
+        }
+    }
+}
+
+fn shift(span: Span, offset: usize) -> Span {
+    Span::new(span.file, span.start() + offset, span.end() + offset)
+}
+
+/// The offset of the last line break in the text that isn't part of a line
+/// splice, or zero if there is none. The line endings of the text are
+/// normalized, so every line break is a LF.
+fn complete_lines_end(text: &str, trigraphs: bool) -> usize {
+    let mut end = text.len();
+
+    while let Some(line_break) = text[..end].rfind('\n') {
+        let before = &text[..line_break];
+        let is_splice = before.ends_with('\\') || (trigraphs && before.ends_with("??/"));
+        if !is_splice {
+            return line_break;
+        }
+
+        end = line_break;
+    }
+
+    0
+}
+
+#[cfg(test)]
+mod tests {
+    use rstest::rstest;
+    use super::*;
+    use pretty_assertions::assert_eq;
+    use crate::{
+        DecodedSource,
+        Language,
+        SecurityChecks,
+    };
+
+    /// Lexes the whole input at once, with the diagnostics sorted by their
+    /// position, as the decoding problems are reported first.
+    fn lex(bytes: &[u8], options: &LexerOptions) -> (Vec<PreprocessorToken<'static>>, Vec<Diagnostic>) {
+        let source = DecodedSource::decode(bytes, InputCharset::Utf8);
+        let mut lexer = Lexer::from_decoded(&source, options.clone());
+        let tokens = lexer.by_ref().map(PreprocessorToken::into_owned).collect();
+
+        let mut diagnostics = lexer.take_diagnostics();
+        diagnostics.sort_by_key(|diagnostic| diagnostic.span.start());
+        (tokens, diagnostics)
+    }
+
+    fn lex_streaming(bytes: &[u8], chunk_size: usize, options: &LexerOptions) -> (Vec<PreprocessorToken<'static>>, Vec<Diagnostic>) {
+        let mut lexer = StreamingLexer::new(bytes, options.clone()).with_chunk_size(chunk_size);
+        let tokens = lexer.by_ref().collect::<io::Result<_>>().unwrap();
+
+        let mut diagnostics = lexer.take_diagnostics();
+        diagnostics.sort_by_key(|diagnostic| diagnostic.span.start());
+        (tokens, diagnostics)
+    }
+
+    #[rstest]
+    #[case("int main() {\n    return 0;\n}\n")]
+    #[case("#include <stdio.h>\n#include \"a.h\"\nint x;")]
+    #[case("#define x \\\n  12\\\r\n34 \\\r\nx\n")]
+    #[case("a /* multi\nline\ncomment */ b // line \\\n comment\nc")]
+    #[case("char *s = \"abc\\\ndef\";\n'x' \"unterminated\nnext")]
+    #[case("/* unterminated\n\n")]
+    #[case("x ??/\ny ??= ??(\n")]
+    #[case("é = \"😀\";\r\nΩ\r\n")]
+    #[case("a.b ... x->y <<= %:%: <::> \\u00e9x\n")]
+    #[case("\n\n\n   \n")]
+    #[case("")]
+    fn matches_lexer(
+        #[case] source_code: &str,
+        #[values(1, 2, 3, 7, 64)] chunk_size: usize,
+        #[values(false, true)] trivia: bool,
+        #[values(false, true)] trigraphs: bool,
+    ) {
+        let options = LexerOptions {
+            trivia,
+            trigraphs,
+            ..Default::default()
+        };
+
+        assert_eq!(lex_streaming(source_code.as_bytes(), chunk_size, &options), lex(source_code.as_bytes(), &options));
+    }
+
+    #[rstest]
+    #[case("auto s = R\"x(\n)\"\n)x\"_s;\n")]
+    #[case("a <=> b ->* c\n")]
+    fn matches_cpp_lexer(#[case] source_code: &str, #[values(1, 5)] chunk_size: usize) {
+        let options = LexerOptions {
+            language: Language::Cpp,
+            ..Default::default()
+        };
+
+        assert_eq!(lex_streaming(source_code.as_bytes(), chunk_size, &options), lex(source_code.as_bytes(), &options));
+    }
+
+    #[rstest]
+    fn matches_lexer_security_checks(#[values(1, 4, 64)] chunk_size: usize) {
+        let source_code = "int a;\nint \u{430};\n/* \u{202E} */ int \u{430}, a;\n\u{405}cope x; Scope";
+        let options = LexerOptions {
+            security: SecurityChecks::ALL,
+            ..Default::default()
+        };
+
+        let expected = lex(source_code.as_bytes(), &options);
+        assert_eq!(expected.1.len(), 3);
+        assert_eq!(lex_streaming(source_code.as_bytes(), chunk_size, &options), expected);
+    }
+
+    #[rstest]
+    #[case(b"int \xFF x;\n")]
+    #[case(b"\xEF\xBB\xBFint x;\n")]
+    #[case(b"\xEF\xBB\xBFint \x80;\n")]
+    #[case(b"a \xE2\x82 b")]
+    #[case(b"a \xF0\x9F\x98")]
+    #[case(b"a\r\nb\r\n")]
+    #[case(b"a\rb\rc")]
+    #[case(b"#define x 1 \\\r\n  + 2\r\r\nx \"\\\r\" \xFF\r")]
+    #[case(b"\xEF\xBB\xBF// comment\r\n\xEF\xBB\xBF\r")]
+    #[case(b"\xEF\xBB")]
+    fn decodes_like_decoded_source(#[case] bytes: &[u8], #[values(1, 2, 3, 64)] chunk_size: usize) {
+        let options = LexerOptions::default();
+        assert_eq!(lex_streaming(bytes, chunk_size, &options), lex(bytes, &options));
+    }
+
+    #[rstest]
+    fn maps_offsets_to_input(#[values(1, 2, 64)] chunk_size: usize) {
+        let bytes = b"\xEF\xBB\xBFa\r\nb\rc \xFF d";
+        let source = DecodedSource::decode(bytes, InputCharset::Utf8);
+
+        let mut lexer = StreamingLexer::new(bytes.as_slice(), LexerOptions::default()).with_chunk_size(chunk_size);
+        let spans: Vec<_> = lexer.by_ref()
+            .map(|token| token.unwrap().span)
+            .collect();
+
+        assert_eq!(spans.iter().map(|span| lexer.original_span(*span)).collect::<Vec<_>>(), vec![
+            Span::detached(3, 4),
+            Span::detached(6, 7),
+            Span::detached(8, 9),
+            Span::detached(10, 11),
+            Span::detached(12, 13),
+        ]);
+
+        for span in spans {
+            assert_eq!(lexer.original_span(span), source.original_span(span));
+        }
+    }
+
+    #[test]
+    fn offsets_are_global() {
+        let source_code = "int x;\n".repeat(1000);
+        let mut lexer = StreamingLexer::with_file(source_code.as_bytes(), FileId::DETACHED, LexerOptions::default())
+            .with_chunk_size(10);
+
+        let last = lexer.by_ref().last().unwrap().unwrap();
+        assert_eq!(last.span, Span::detached(source_code.len() - 2, source_code.len() - 1));
+        assert!(lexer.text.capacity() < 100, "the whole input was kept in memory");
+    }
+
+    #[test]
+    fn read_error() {
+        struct FailingReader;
+
+        impl Read for FailingReader {
+            fn read(&mut self, _: &mut [u8]) -> io::Result<usize> {
+                Err(io::Error::other("disk on fire"))
+            }
+        }
+
+        let mut lexer = StreamingLexer::new(FailingReader, LexerOptions::default());
+        assert_eq!(lexer.next().map(|result| result.map_err(|error| error.to_string())), Some(Err("disk on fire".into())));
+    }
+
+    #[rstest]
+    #[case("", 0)]
+    #[case("abc", 0)]
+    #[case("a\nb", 1)]
+    #[case("a\nb\\\nc", 1)]
+    #[case("a\\\nb\nc", 4)]
+    #[case("a\nb??/\nc", 1)]
+    fn complete_lines(#[case] text: &str, #[case] expected: usize) {
+        assert_eq!(complete_lines_end(text, true), expected);
+    }
+
+    proptest::proptest! {
+        #[test]
+        fn matches_lexer_for_any_input(
+            source_code in "[a-c /*\"'\\\\\n\r#<>?=()u0-9é]{0,60}",
+            chunk_size in 1..16usize,
+            trivia: bool,
+            trigraphs: bool,
+        ) {
+            let options = LexerOptions {
+                trivia,
+                trigraphs,
+                ..Default::default()
+            };
+
+            proptest::prop_assert_eq!(lex_streaming(source_code.as_bytes(), chunk_size, &options), lex(source_code.as_bytes(), &options));
+        }
+    }
+}
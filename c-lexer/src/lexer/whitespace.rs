@@ -0,0 +1,144 @@
+// Copyright (C) 2023 Tristan Gerritsen <tristan@thewoosh.org>
+// All Rights Reserved.
+
+use std::borrow::Cow;
+
+use crate::{
+    PreprocessorToken,
+    PreprocessorTokenKind,
+    TokenFlags,
+};
+
+use super::{
+    source_code::ByteSet,
+    Lexer,
+};
+
+const WHITESPACE: ByteSet = ByteSet::new(b" \t\n\r\x0B\x0C");
+
+impl<'source_code> Lexer<'source_code> {
+    /// Consumes a run of white-space characters (ISO/IEC 9899 6.4p3).
+    pub(super) fn consume_whitespace(&mut self) -> PreprocessorToken<'source_code> {
+        let start = self.source_code.index();
+
+        loop {
+            _ = self.source_code.consume_ascii_while(&WHITESPACE);
+
+            match self.source_code.peek() {
+                Some(char) if is_whitespace(char) => _ = self.source_code.next(),
+                _ => break,
+            }
+        }
+
+        let end = self.source_code.index();
+        self.token(PreprocessorTokenKind::Whitespace(self.cleaned(start, end)), start, end)
+    }
+
+    /// Line splices belong to the character after them, but source code that
+    /// consists of nothing but line splices has no such character. To keep
+    /// the token stream lossless, they become an empty whitespace token.
+    pub(super) fn consume_dangling_line_splices(&mut self) -> Option<PreprocessorToken<'source_code>> {
+        if self.source_code.position().index() != 0 || self.source_code.as_str().is_empty() {
+            return None;
+        }
+
+        self.source_code.skip_to_end();
+
+        let end = self.source_code.index();
+        Some(self.token(PreprocessorTokenKind::Whitespace(Cow::Borrowed("")), 0, end))
+    }
+
+    /// Folds whitespace and comments into the flags of the token after them,
+    /// and returns whether the token was one of those.
+    pub(super) fn update_next_flags(&mut self, token: &PreprocessorToken<'source_code>) -> bool {
+        match &token.kind {
+            PreprocessorTokenKind::Whitespace(whitespace) => {
+                self.next_flags.at_start_of_line |= whitespace.contains('\n');
+                self.next_flags.has_leading_whitespace = true;
+                true
+            }
+
+            PreprocessorTokenKind::Comment(..) => {
+                self.next_flags.has_leading_whitespace = true;
+                true
+            }
+
+            _ => {
+                self.next_flags = TokenFlags::default();
+                false
+            }
+        }
+    }
+}
+
+pub(super) const fn is_whitespace(char: char) -> bool {
+    matches!(char, ' ' | '\t' | '\n' | '\r' | '\x0B' | '\x0C')
+}
+
+#[cfg(test)]
+mod tests {
+    use rstest::rstest;
+    use super::*;
+    use pretty_assertions::assert_eq;
+    use crate::LexerOptions;
+
+    fn trivia_options() -> LexerOptions {
+        LexerOptions {
+            trivia: true,
+            ..Default::default()
+        }
+    }
+
+    fn flags(at_start_of_line: bool, has_leading_whitespace: bool) -> TokenFlags {
+        TokenFlags { at_start_of_line, has_leading_whitespace }
+    }
+
+    #[rstest]
+    #[case("a b", &[flags(true, false), flags(false, true)])]
+    #[case("a\nb", &[flags(true, false), flags(true, true)])]
+    #[case("  a", &[flags(true, true)])]
+    #[case("a/**/b", &[flags(true, false), flags(false, true)])]
+    #[case("a /*\n*/ b", &[flags(true, false), flags(false, true)])]
+    #[case("a // c\nb", &[flags(true, false), flags(true, true)])]
+    #[case("a\\\nb", &[flags(true, false)])]
+    #[case("a \\\n b", &[flags(true, false), flags(false, true)])]
+    #[case("a\t\x0B\x0Cb", &[flags(true, false), flags(false, true)])]
+    #[case("f(x)", &[flags(true, false), flags(false, false), flags(false, false), flags(false, false)])]
+    fn folded_into_flags(#[case] input: &str, #[case] expected: &[TokenFlags]) {
+        let actual: Vec<_> = Lexer::new(input)
+            .map(|token| token.flags)
+            .collect();
+
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn trivia() {
+        let input = "a  /* c */\n\tb";
+        let actual: Vec<_> = Lexer::with_options(input, trivia_options())
+            .map(|token| (token.kind, token.flags))
+            .collect();
+
+        assert_eq!(actual, [
+            (PreprocessorTokenKind::Identifier("a".into()), flags(true, false)),
+            (PreprocessorTokenKind::Whitespace("  ".into()), flags(false, false)),
+            (PreprocessorTokenKind::Comment("/* c */".into()), flags(false, true)),
+            (PreprocessorTokenKind::Whitespace("\n\t".into()), flags(false, true)),
+            (PreprocessorTokenKind::Identifier("b".into()), flags(true, true)),
+        ]);
+    }
+
+    #[rstest]
+    #[case("int  main ( ) {\n\treturn 0 ;\n}\n")]
+    #[case("  // only trivia\n/* */ ")]
+    #[case("a \\\n b")]
+    fn trivia_covers_source_code(#[case] input: &str) {
+        let mut end = 0;
+        for token in Lexer::with_options(input, trivia_options()) {
+            assert_eq!(token.span.start(), end);
+            end = token.span.end();
+        }
+
+        assert_eq!(end, input.len());
+    }
+}
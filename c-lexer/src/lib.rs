@@ -1,19 +1,77 @@
 // Copyright (C) 2023 Tristan Gerritsen <tristan@thewoosh.org>
 // All Rights Reserved.
 
+mod diagnostic;
+mod encoding;
 mod lexer;
+mod source_map;
+mod symbol;
 mod token;
 
 pub use self::{
-    lexer::Lexer,
+    diagnostic::{
+        Diagnostic,
+        DiagnosticKind,
+        Severity,
+    },
+    encoding::{
+        DecodedSource,
+        InputCharset,
+        UnknownInputCharset,
+    },
+    lexer::{
+        Checkpoint,
+        Dialect,
+        Language,
+        LanguageStandard,
+        LexedFile,
+        Lexer,
+        LexerOptions,
+        ParallelLexer,
+        SecurityChecks,
+        StreamingLexer,
+        TextEdit,
+        TokenStream,
+        UnknownLanguage,
+        UnknownLanguageStandard,
+    },
+    source_map::{
+        FileId,
+        Location,
+        SourceFile,
+        SourceMap,
+        Span,
+    },
+    symbol::Symbol,
     token::{
+        CharacterConstant,
+        Constant,
+        ConstantError,
+        Encoding,
+        FloatingConstant,
+        FloatingSuffix,
+        HeaderName,
+        HeaderNameForm,
         IdentifierIsNotAKeyword,
+        IntegerConstant,
+        IntegerLength,
+        IntegerSuffix,
+        IntegerType,
         Keyword,
+        LiteralError,
         PreprocessorToken,
         PreprocessorTokenKind,
         PreprocessorTokenNotMappedToTokenError,
         Punctuator,
+        Radix,
+        StringLiteral,
         Token,
+        TokenBuffer,
+        TokenBufferIter,
+        TokenFlags,
         TokenKind,
+        TokenKindView,
+        TokenView,
+        UnknownPunctuator,
     },
 };
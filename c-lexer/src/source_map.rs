@@ -0,0 +1,347 @@
+// Copyright (C) 2023 Tristan Gerritsen <tristan@thewoosh.org>
+// All Rights Reserved.
+
+use std::{
+    fmt::Display,
+    ops::Range,
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    encoding::OffsetMap,
+    DecodedSource,
+    Diagnostic,
+    InputCharset,
+    Lexer,
+    LexerOptions,
+};
+
+/// Identifies a file in a [`SourceMap`].
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct FileId(u32);
+
+impl FileId {
+    /// The file of text that was lexed without a [`SourceMap`], e.g. using
+    /// [`Lexer::new`].
+    pub const DETACHED: Self = Self(u32::MAX);
+}
+
+/// The range of bytes a token or diagnostic covers in a file. Offsets are
+/// stored as `u32` to keep tokens small, which limits files to 4 GiB.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Span {
+    pub file: FileId,
+    pub start: u32,
+    pub end: u32,
+}
+
+impl Span {
+    pub fn new(file: FileId, start: usize, end: usize) -> Self {
+        debug_assert!(start <= end);
+        Self {
+            file,
+            start: start.try_into().expect("file larger than 4 GiB"),
+            end: end.try_into().expect("file larger than 4 GiB"),
+        }
+    }
+
+    /// A span in text that isn't part of a [`SourceMap`].
+    pub fn detached(start: usize, end: usize) -> Self {
+        Self::new(FileId::DETACHED, start, end)
+    }
+
+    pub const fn start(&self) -> usize {
+        self.start as usize
+    }
+
+    pub const fn end(&self) -> usize {
+        self.end as usize
+    }
+
+    pub const fn range(&self) -> Range<usize> {
+        self.start()..self.end()
+    }
+
+    pub const fn len(&self) -> usize {
+        (self.end - self.start) as usize
+    }
+
+    pub const fn is_empty(&self) -> bool {
+        self.start == self.end
+    }
+
+    /// The span from the start of this span up to the end of the other one,
+    /// which must be in the same file.
+    pub fn to(&self, other: Span) -> Span {
+        debug_assert_eq!(self.file, other.file);
+        Span {
+            file: self.file,
+            start: self.start.min(other.start),
+            end: self.end.max(other.end),
+        }
+    }
+}
+
+/// A position in a file, where all fields are 1-based.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Location {
+    pub line: u32,
+
+    /// The column in bytes of UTF-8, as used in compiler diagnostics.
+    pub column: u32,
+
+    /// The column in UTF-16 code units, as used by most editors and the
+    /// Language Server Protocol.
+    pub utf16_column: u32,
+}
+
+impl Display for Location {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}:{}", self.line, self.column)
+    }
+}
+
+/// A file loaded into a [`SourceMap`], together with the offsets at which its
+/// lines start.
+#[derive(Debug)]
+pub struct SourceFile {
+    path: PathBuf,
+    text: String,
+    line_starts: Vec<u32>,
+    offsets: OffsetMap,
+
+    /// The problems found whilst decoding the file.
+    diagnostics: Vec<Diagnostic>,
+}
+
+impl SourceFile {
+    fn new(path: PathBuf, text: String, offsets: OffsetMap, diagnostics: Vec<Diagnostic>) -> Self {
+        assert!(u32::try_from(text.len()).is_ok(), "file larger than 4 GiB");
+
+        let line_starts = std::iter::once(0)
+            .chain(text.match_indices('\n').map(|(index, _)| index as u32 + 1))
+            .collect();
+
+        Self { path, text, line_starts, offsets, diagnostics }
+    }
+
+    /// Decodes the file, of which the diagnostics are detached until it is
+    /// added to a [`SourceMap`].
+    pub(crate) fn decode(path: PathBuf, bytes: &[u8], charset: InputCharset) -> Self {
+        let (text, offsets, diagnostics) = DecodedSource::decode(bytes, charset).into_parts();
+        Self::new(path, text.into_owned(), offsets, diagnostics)
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    pub fn line_count(&self) -> usize {
+        self.line_starts.len()
+    }
+
+    /// The text of the given 1-based line, without the line terminator.
+    pub fn line(&self, line: u32) -> Option<&str> {
+        let start = *self.line_starts.get(line.checked_sub(1)? as usize)? as usize;
+        let end = self.line_starts.get(line as usize)
+            .map_or(self.text.len(), |next| *next as usize - 1);
+
+        let line = &self.text[start..end];
+        Some(line.strip_suffix('\r').unwrap_or(line))
+    }
+
+    /// Resolves the byte offset to a location, using a binary search over the
+    /// line starts.
+    pub fn location(&self, offset: usize) -> Location {
+        assert!(offset <= self.text.len(), "offset {offset} out of bounds for {}", self.path.display());
+
+        let line_index = self.line_starts.partition_point(|start| *start as usize <= offset) - 1;
+        let line_start = self.line_starts[line_index] as usize;
+        let before = &self.text[line_start..offset];
+
+        Location {
+            line: line_index as u32 + 1,
+            column: before.len() as u32 + 1,
+            utf16_column: before.encode_utf16().count() as u32 + 1,
+        }
+    }
+
+    /// The byte offset in the file on disk of the offset in the text, which
+    /// differ when the file was decoded, see [`DecodedSource`].
+    pub fn original_offset(&self, offset: usize) -> usize {
+        self.offsets.original_offset(offset)
+    }
+}
+
+/// Owns the files of a translation unit, so that a [`Span`] can be resolved
+/// to the file, text and line/column it refers to.
+#[derive(Debug, Default)]
+pub struct SourceMap {
+    files: Vec<SourceFile>,
+}
+
+impl SourceMap {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn add_file(&mut self, path: impl Into<PathBuf>, text: impl Into<String>) -> FileId {
+        let id = self.next_id();
+        self.files.push(SourceFile::new(path.into(), text.into(), OffsetMap::default(), Vec::new()));
+        id
+    }
+
+    /// Decodes the bytes and adds them as a file. Problems with the encoding
+    /// are reported by the lexers of the file.
+    pub fn add_bytes(&mut self, path: impl Into<PathBuf>, bytes: &[u8], charset: InputCharset) -> FileId {
+        self.add_source_file(SourceFile::decode(path.into(), bytes, charset))
+    }
+
+    /// Adds a file that was decoded without the map, e.g. on another thread.
+    pub(crate) fn add_source_file(&mut self, mut file: SourceFile) -> FileId {
+        let id = self.next_id();
+        for diagnostic in &mut file.diagnostics {
+            diagnostic.span.file = id;
+        }
+
+        self.files.push(file);
+        id
+    }
+
+    /// Reads the file at the path and adds it, see [`SourceMap::add_bytes`].
+    pub fn load(&mut self, path: impl AsRef<Path>, charset: InputCharset) -> std::io::Result<FileId> {
+        let path = path.as_ref();
+        let bytes = std::fs::read(path)?;
+        Ok(self.add_bytes(path, &bytes, charset))
+    }
+
+    fn next_id(&self) -> FileId {
+        let id = FileId(self.files.len() as u32);
+        assert_ne!(id, FileId::DETACHED, "too many files");
+        id
+    }
+
+    /// Panics if the file isn't part of this map, e.g. [`FileId::DETACHED`].
+    pub fn file(&self, id: FileId) -> &SourceFile {
+        &self.files[id.0 as usize]
+    }
+
+    pub fn files(&self) -> impl Iterator<Item = (FileId, &SourceFile)> {
+        self.files.iter()
+            .enumerate()
+            .map(|(index, file)| (FileId(index as u32), file))
+    }
+
+    /// Creates a lexer for the file, of which the tokens carry its [`FileId`].
+    /// The diagnostics of decoding the file are reported by the lexer.
+    pub fn lexer(&self, id: FileId, options: LexerOptions) -> Lexer<'_> {
+        let file = self.file(id);
+        Lexer::with_file(file.text(), id, options)
+            .with_diagnostics(file.diagnostics.clone())
+    }
+
+    /// The original text the span covers.
+    pub fn text(&self, span: Span) -> &str {
+        &self.file(span.file).text()[span.range()]
+    }
+
+    /// The locations of the start and end of the span.
+    pub fn resolve(&self, span: Span) -> (Location, Location) {
+        let file = self.file(span.file);
+        (file.location(span.start()), file.location(span.end()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rstest::rstest;
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    fn location(line: u32, column: u32, utf16_column: u32) -> Location {
+        Location { line, column, utf16_column }
+    }
+
+    #[rstest]
+    #[case("", 0, location(1, 1, 1))]
+    #[case("abc", 2, location(1, 3, 3))]
+    #[case("abc", 3, location(1, 4, 4))]
+    #[case("a\nb", 1, location(1, 2, 2))]
+    #[case("a\nb", 2, location(2, 1, 1))]
+    #[case("a\r\nb", 3, location(2, 1, 1))]
+    #[case("\n\n\nx", 3, location(4, 1, 1))]
+    #[case("é = 1", 3, location(1, 4, 3))]
+    #[case("s = \"😀\";", 9, location(1, 10, 8))]
+    fn resolve_location(#[case] text: &str, #[case] offset: usize, #[case] expected: Location) {
+        let mut source_map = SourceMap::new();
+        let file = source_map.add_file("test.c", text);
+        assert_eq!(source_map.file(file).location(offset), expected);
+    }
+
+    #[rstest]
+    #[case("a\r\nbc\n\nd", 1, Some("a"))]
+    #[case("a\r\nbc\n\nd", 2, Some("bc"))]
+    #[case("a\r\nbc\n\nd", 3, Some(""))]
+    #[case("a\r\nbc\n\nd", 4, Some("d"))]
+    #[case("a\r\nbc\n\nd", 5, None)]
+    #[case("a\n", 2, Some(""))]
+    #[case("a", 0, None)]
+    fn line_text(#[case] text: &str, #[case] line: u32, #[case] expected: Option<&str>) {
+        let mut source_map = SourceMap::new();
+        let file = source_map.add_file("test.c", text);
+        assert_eq!(source_map.file(file).line(line), expected);
+    }
+
+    #[test]
+    fn spans_of_different_files() {
+        let mut source_map = SourceMap::new();
+        let a = source_map.add_file("a.h", "int a;");
+        let b = source_map.add_file("b.c", "#include \"a.h\"\nint b;");
+        assert_ne!(a, b);
+
+        let tokens: Vec<_> = source_map.lexer(b, LexerOptions::default()).collect();
+
+        let last = tokens[tokens.len() - 2].span;
+        assert_eq!(last.file, b);
+        assert_eq!(source_map.text(last), "b");
+        assert_eq!(source_map.resolve(last), (location(2, 5, 5), location(2, 6, 6)));
+
+        let first = source_map.lexer(a, LexerOptions::default()).next().unwrap().span;
+        assert_eq!(first, Span::new(a, 0, 3));
+        assert_eq!(source_map.text(first), "int");
+    }
+
+    #[test]
+    fn decoded_file() {
+        let mut source_map = SourceMap::new();
+        let file = source_map.add_bytes("latin1.c", b"/* caf\xE9 */\r\nint \xFF;", InputCharset::Latin1);
+        assert_eq!(source_map.file(file).text(), "/* café */\nint ÿ;");
+
+        let mut lexer = source_map.lexer(file, LexerOptions::default());
+        let tokens: Vec<_> = lexer.by_ref().collect();
+        assert_eq!(source_map.resolve(tokens[1].span).0, location(2, 5, 5));
+        assert_eq!(source_map.file(file).original_offset(tokens[1].span.start()), 16);
+        assert_eq!(lexer.diagnostics(), &[]);
+    }
+
+    #[test]
+    fn decoding_problems_are_reported_by_lexer() {
+        let mut source_map = SourceMap::new();
+        source_map.add_file("a.c", "");
+        let file = source_map.add_bytes("b.c", b"int\xC0;", InputCharset::Utf8);
+
+        let mut lexer = source_map.lexer(file, LexerOptions::default());
+        lexer.by_ref().for_each(drop);
+        assert_eq!(lexer.diagnostics(), &[Diagnostic {
+            kind: crate::DiagnosticKind::InvalidEncoding {
+                charset: InputCharset::Utf8,
+                offset: 3,
+            },
+            span: Span::new(file, 3, 6),
+        }]);
+    }
+}
@@ -0,0 +1,236 @@
+// Copyright (C) 2023 Tristan Gerritsen <tristan@thewoosh.org>
+// All Rights Reserved.
+
+use std::{
+    cell::RefCell,
+    collections::HashMap,
+    fmt::{Debug, Display},
+    hash::{BuildHasherDefault, Hasher},
+    sync::{LazyLock, RwLock},
+};
+
+use strum::{
+    EnumCount,
+    IntoEnumIterator,
+};
+
+use crate::Keyword;
+
+/// An interned identifier, which is a small handle that is compared and
+/// hashed in O(1). The spelling lives for the rest of the program, in an
+/// interner shared by all lexers.
+///
+/// The spellings of the keywords are interned first, in the order of
+/// [`Keyword`], so finding out whether a symbol is a keyword doesn't need a
+/// lookup.
+///
+/// Note that ordering compares the order of interning, not the spelling.
+#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Symbol(u32);
+
+impl Symbol {
+    pub fn intern(string: &str) -> Self {
+        CACHE.with_borrow_mut(|cache| {
+            if let Some(symbol) = cache.get(string) {
+                return *symbol;
+            }
+
+            let (string, symbol) = Self::intern_shared(string);
+            cache.insert(string, symbol);
+            symbol
+        })
+    }
+
+    fn intern_shared(string: &str) -> (&'static str, Self) {
+        if let Some((string, symbol)) = INTERNER.read().unwrap().symbols.get_key_value(string) {
+            return (string, *symbol);
+        }
+
+        let mut interner = INTERNER.write().unwrap();
+        let symbol = interner.intern(string);
+        (interner.strings[symbol.0 as usize], symbol)
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        INTERNER.read().unwrap().strings[self.0 as usize]
+    }
+
+    /// The keyword with this spelling, regardless of the language standard.
+    pub fn keyword(&self) -> Option<Keyword> {
+        if self.0 as usize >= Keyword::COUNT {
+            return None;
+        }
+
+        Keyword::from_repr(self.0 as u8)
+    }
+}
+
+impl From<&str> for Symbol {
+    fn from(value: &str) -> Self {
+        Self::intern(value)
+    }
+}
+
+impl From<Keyword> for Symbol {
+    fn from(value: Keyword) -> Self {
+        Self(value as u32)
+    }
+}
+
+impl PartialEq<str> for Symbol {
+    fn eq(&self, other: &str) -> bool {
+        self.as_str() == other
+    }
+}
+
+impl PartialEq<&str> for Symbol {
+    fn eq(&self, other: &&str) -> bool {
+        self.as_str() == *other
+    }
+}
+
+impl Debug for Symbol {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_tuple("Symbol").field(&self.as_str()).finish()
+    }
+}
+
+impl Display for Symbol {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+static INTERNER: LazyLock<RwLock<Interner>> = LazyLock::new(|| {
+    let mut interner = Interner::default();
+    for keyword in Keyword::iter() {
+        let symbol = interner.intern(keyword.as_ref());
+        debug_assert_eq!(symbol, Symbol::from(keyword));
+    }
+
+    RwLock::new(interner)
+});
+
+thread_local! {
+    /// The symbols this thread has seen, so that lexers running on many
+    /// threads at once don't contend on the lock of the interner for every
+    /// identifier.
+    static CACHE: RefCell<HashMap<&'static str, Symbol, BuildHasherDefault<FxHasher>>> = RefCell::default();
+}
+
+/// The spellings are leaked, as symbols can't tell when they are no longer
+/// in use.
+#[derive(Default)]
+struct Interner {
+    symbols: HashMap<&'static str, Symbol, BuildHasherDefault<FxHasher>>,
+    strings: Vec<&'static str>,
+}
+
+impl Interner {
+    fn intern(&mut self, string: &str) -> Symbol {
+        if let Some(symbol) = self.symbols.get(string) {
+            return *symbol;
+        }
+
+        let symbol = Symbol(self.strings.len().try_into().expect("too many symbols"));
+        let string: &'static str = Box::leak(string.into());
+
+        self.strings.push(string);
+        self.symbols.insert(string, symbol);
+        symbol
+    }
+}
+
+/// The hash function of Firefox and rustc, which is much faster than the
+/// default SipHash for the short strings identifiers usually are. Resistance
+/// against collision attacks isn't needed for source code.
+#[derive(Default)]
+struct FxHasher {
+    hash: u64,
+}
+
+impl FxHasher {
+    const SEED: u64 = 0x51_7c_c1_b7_27_22_0a_95;
+
+    fn add_to_hash(&mut self, word: u64) {
+        self.hash = (self.hash.rotate_left(5) ^ word).wrapping_mul(Self::SEED);
+    }
+}
+
+impl Hasher for FxHasher {
+    fn write(&mut self, bytes: &[u8]) {
+        let mut chunks = bytes.chunks_exact(8);
+        for chunk in &mut chunks {
+            self.add_to_hash(u64::from_le_bytes(chunk.try_into().unwrap()));
+        }
+
+        for byte in chunks.remainder() {
+            self.add_to_hash(*byte as u64);
+        }
+    }
+
+    fn write_u8(&mut self, value: u8) {
+        self.add_to_hash(value as u64);
+    }
+
+    fn write_usize(&mut self, value: usize) {
+        self.add_to_hash(value as u64);
+    }
+
+    fn finish(&self) -> u64 {
+        self.hash
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rstest::rstest;
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn same_spelling_same_symbol() {
+        let a = Symbol::intern("some_identifier");
+        let b = Symbol::intern(&String::from("some_identifier"));
+        let c = Symbol::intern("some_other_identifier");
+
+        assert_eq!(a, b);
+        assert_ne!(a, c);
+        assert_eq!(a.as_str(), "some_identifier");
+        assert_eq!(c.to_string(), "some_other_identifier");
+    }
+
+    #[test]
+    fn same_symbol_on_other_threads() {
+        let symbols: Vec<_> = (0..4)
+            .map(|index| std::thread::spawn(move || {
+                (Symbol::intern("shared_between_threads"), Symbol::intern(&format!("thread_{index}")))
+            }))
+            .collect::<Vec<_>>()
+            .into_iter()
+            .map(|thread| thread.join().unwrap())
+            .collect();
+
+        for (index, (shared, own)) in symbols.into_iter().enumerate() {
+            assert_eq!(shared, Symbol::intern("shared_between_threads"));
+            assert_eq!(own.as_str(), format!("thread_{index}"));
+        }
+    }
+
+    #[rstest]
+    #[case("int", Some(Keyword::Int))]
+    #[case("_Bool", Some(Keyword::UnderscoreBool))]
+    #[case("typeof_unqual", Some(Keyword::TypeofUnqual))]
+    #[case("main", None)]
+    #[case("Int", None)]
+    fn keyword(#[case] spelling: &str, #[case] expected: Option<Keyword>) {
+        assert_eq!(Symbol::intern(spelling).keyword(), expected);
+    }
+
+    #[test]
+    fn keywords_are_interned_in_order() {
+        for keyword in Keyword::iter() {
+            assert_eq!(Symbol::intern(keyword.as_ref()), Symbol::from(keyword));
+        }
+    }
+}
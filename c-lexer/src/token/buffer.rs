@@ -0,0 +1,371 @@
+// Copyright (C) 2023 Tristan Gerritsen <tristan@thewoosh.org>
+// All Rights Reserved.
+
+use std::{
+    fmt::Display,
+    iter::FusedIterator,
+    mem::size_of,
+    ops::Range,
+};
+
+use crate::{
+    Constant,
+    FileId,
+    Keyword,
+    Punctuator,
+    Span,
+    StringLiteral,
+    Symbol,
+    Token,
+    TokenKind,
+};
+
+/// The tokens of a file stored as a struct of arrays, which takes a fraction
+/// of the memory of a `Vec<Token>`: every token is a one-byte tag, a `u32`
+/// start and length, and a `u32` payload. Keywords and punctuators are stored
+/// in the payload itself, for the other kinds it is an index into the side
+/// table of that kind.
+///
+/// All tokens must be of the same file, which is stored only once.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct TokenBuffer {
+    file: Option<FileId>,
+
+    tags: Vec<TokenTag>,
+    starts: Vec<u32>,
+    lengths: Vec<u32>,
+    payloads: Vec<u32>,
+
+    symbols: Vec<Symbol>,
+    constants: Vec<Constant>,
+    string_literals: Vec<StringLiteral>,
+    user_defined_literals: Vec<(TokenKind, Symbol)>,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[repr(u8)]
+enum TokenTag {
+    Keyword,
+    Identifier,
+    Constant,
+    StringLiteral,
+    Punctuator,
+    UserDefinedLiteral,
+}
+
+/// A token in a [`TokenBuffer`], of which the payload is borrowed from the
+/// buffer.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct TokenView<'buffer> {
+    pub kind: TokenKindView<'buffer>,
+    pub span: Span,
+}
+
+/// The borrowed counterpart of [`TokenKind`].
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum TokenKindView<'buffer> {
+    Keyword(Keyword),
+    Identifier(Symbol),
+    Constant(&'buffer Constant),
+    StringLiteral(&'buffer StringLiteral),
+    Punctuator(Punctuator),
+    UserDefinedLiteral(&'buffer TokenKind, Symbol),
+}
+
+impl TokenBuffer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self {
+            tags: Vec::with_capacity(capacity),
+            starts: Vec::with_capacity(capacity),
+            lengths: Vec::with_capacity(capacity),
+            payloads: Vec::with_capacity(capacity),
+            ..Default::default()
+        }
+    }
+
+    /// The file of the tokens, which is `None` until a token is pushed.
+    pub fn file(&self) -> Option<FileId> {
+        self.file
+    }
+
+    pub fn len(&self) -> usize {
+        self.tags.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.tags.is_empty()
+    }
+
+    /// Panics if the token is of another file than the tokens before it.
+    pub fn push(&mut self, token: Token) {
+        let file = *self.file.get_or_insert(token.span.file);
+        assert_eq!(token.span.file, file, "all tokens of a buffer must be of the same file");
+
+        let (tag, payload) = match token.kind {
+            TokenKind::Keyword(keyword) => (TokenTag::Keyword, keyword as u32),
+            TokenKind::Identifier(symbol) => (TokenTag::Identifier, push_index(&mut self.symbols, symbol)),
+            TokenKind::Constant(constant) => (TokenTag::Constant, push_index(&mut self.constants, constant)),
+            TokenKind::StringLiteral(literal) => (TokenTag::StringLiteral, push_index(&mut self.string_literals, literal)),
+            TokenKind::Punctuator(punctuator) => (TokenTag::Punctuator, punctuator as u32),
+            TokenKind::UserDefinedLiteral(literal, suffix) => {
+                (TokenTag::UserDefinedLiteral, push_index(&mut self.user_defined_literals, (*literal, suffix)))
+            }
+        };
+
+        self.tags.push(tag);
+        self.starts.push(token.span.start);
+        self.lengths.push(token.span.end - token.span.start);
+        self.payloads.push(payload);
+    }
+
+    pub fn get(&self, index: usize) -> Option<TokenView<'_>> {
+        let tag = *self.tags.get(index)?;
+        let payload = self.payloads[index];
+
+        let kind = match tag {
+            TokenTag::Keyword => TokenKindView::Keyword(Keyword::from_repr(payload as u8)?),
+            TokenTag::Identifier => TokenKindView::Identifier(self.symbols[payload as usize]),
+            TokenTag::Constant => TokenKindView::Constant(&self.constants[payload as usize]),
+            TokenTag::StringLiteral => TokenKindView::StringLiteral(&self.string_literals[payload as usize]),
+            TokenTag::Punctuator => TokenKindView::Punctuator(Punctuator::from_repr(payload as u8)?),
+            TokenTag::UserDefinedLiteral => {
+                let (literal, suffix) = &self.user_defined_literals[payload as usize];
+                TokenKindView::UserDefinedLiteral(literal, *suffix)
+            }
+        };
+
+        let start = self.starts[index];
+        Some(TokenView {
+            kind,
+            span: Span {
+                file: self.file.unwrap_or(FileId::DETACHED),
+                start,
+                end: start + self.lengths[index],
+            },
+        })
+    }
+
+    pub fn iter(&self) -> TokenBufferIter<'_> {
+        TokenBufferIter {
+            buffer: self,
+            range: 0..self.len(),
+        }
+    }
+
+    /// The number of bytes allocated for the arrays and side tables, not
+    /// counting the code units of the literals.
+    pub fn allocated_size(&self) -> usize {
+        self.tags.capacity() * size_of::<TokenTag>()
+            + self.starts.capacity() * size_of::<u32>()
+            + self.lengths.capacity() * size_of::<u32>()
+            + self.payloads.capacity() * size_of::<u32>()
+            + self.symbols.capacity() * size_of::<Symbol>()
+            + self.constants.capacity() * size_of::<Constant>()
+            + self.string_literals.capacity() * size_of::<StringLiteral>()
+            + self.user_defined_literals.capacity() * size_of::<(TokenKind, Symbol)>()
+    }
+
+    pub fn shrink_to_fit(&mut self) {
+        self.tags.shrink_to_fit();
+        self.starts.shrink_to_fit();
+        self.lengths.shrink_to_fit();
+        self.payloads.shrink_to_fit();
+        self.symbols.shrink_to_fit();
+        self.constants.shrink_to_fit();
+        self.string_literals.shrink_to_fit();
+        self.user_defined_literals.shrink_to_fit();
+    }
+}
+
+fn push_index<T>(table: &mut Vec<T>, value: T) -> u32 {
+    let index = table.len().try_into().expect("too many tokens");
+    table.push(value);
+    index
+}
+
+impl Extend<Token> for TokenBuffer {
+    fn extend<T: IntoIterator<Item = Token>>(&mut self, iter: T) {
+        for token in iter {
+            self.push(token);
+        }
+    }
+}
+
+impl FromIterator<Token> for TokenBuffer {
+    fn from_iter<T: IntoIterator<Item = Token>>(iter: T) -> Self {
+        let iter = iter.into_iter();
+
+        let mut buffer = Self::with_capacity(iter.size_hint().0);
+        buffer.extend(iter);
+        buffer
+    }
+}
+
+impl<'buffer> IntoIterator for &'buffer TokenBuffer {
+    type Item = TokenView<'buffer>;
+    type IntoIter = TokenBufferIter<'buffer>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+/// Iterates over the tokens of a [`TokenBuffer`] as [`TokenView`]s.
+#[derive(Clone, Debug)]
+pub struct TokenBufferIter<'buffer> {
+    buffer: &'buffer TokenBuffer,
+    range: Range<usize>,
+}
+
+impl<'buffer> Iterator for TokenBufferIter<'buffer> {
+    type Item = TokenView<'buffer>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.buffer.get(self.range.next()?)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.range.size_hint()
+    }
+}
+
+impl DoubleEndedIterator for TokenBufferIter<'_> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.buffer.get(self.range.next_back()?)
+    }
+}
+
+impl ExactSizeIterator for TokenBufferIter<'_> {}
+
+impl FusedIterator for TokenBufferIter<'_> {}
+
+impl TokenView<'_> {
+    pub fn to_token(&self) -> Token {
+        Token {
+            kind: self.kind.to_token_kind(),
+            span: self.span,
+        }
+    }
+}
+
+impl TokenKindView<'_> {
+    pub fn to_token_kind(&self) -> TokenKind {
+        match *self {
+            Self::Keyword(keyword) => TokenKind::Keyword(keyword),
+            Self::Identifier(symbol) => TokenKind::Identifier(symbol),
+            Self::Constant(constant) => TokenKind::Constant(constant.clone()),
+            Self::StringLiteral(literal) => TokenKind::StringLiteral(literal.clone()),
+            Self::Punctuator(punctuator) => TokenKind::Punctuator(punctuator),
+            Self::UserDefinedLiteral(literal, suffix) => TokenKind::UserDefinedLiteral(Box::new(literal.clone()), suffix),
+        }
+    }
+}
+
+/// Spells the token as C.
+impl Display for TokenView<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        Display::fmt(&self.kind, f)
+    }
+}
+
+/// Spells the token as C.
+impl Display for TokenKindView<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Keyword(keyword) => Display::fmt(keyword, f),
+            Self::Identifier(symbol) => Display::fmt(symbol, f),
+            Self::Constant(constant) => Display::fmt(constant, f),
+            Self::StringLiteral(literal) => Display::fmt(literal, f),
+            Self::Punctuator(punctuator) => Display::fmt(punctuator, f),
+            Self::UserDefinedLiteral(literal, suffix) => write!(f, "{literal}{suffix}"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rstest::rstest;
+    use super::*;
+    use pretty_assertions::assert_eq;
+    use crate::{
+        Language,
+        Lexer,
+        LexerOptions,
+        SourceMap,
+        TokenStream,
+    };
+
+    fn lex(source_code: &str, options: LexerOptions) -> Vec<Token> {
+        TokenStream::new(Lexer::with_options(source_code, options)).collect()
+    }
+
+    #[rstest]
+    #[case("int main(void) { return 0x10u + 'a'; }", Language::C)]
+    #[case("char *s = u8\"abc\" \"def\"; float f = 1.5e3f;", Language::C)]
+    #[case("auto s = \"abc\"_s + 12_km; a <=> b;", Language::Cpp)]
+    #[case("", Language::C)]
+    fn same_tokens(#[case] source_code: &str, #[case] language: Language) {
+        let tokens = lex(source_code, LexerOptions { language, ..Default::default() });
+        let buffer: TokenBuffer = tokens.iter().cloned().collect();
+
+        assert_eq!(buffer.len(), tokens.len());
+        assert_eq!(buffer.iter().map(|token| token.to_token()).collect::<Vec<_>>(), tokens);
+        assert_eq!(buffer.iter().rev().map(|token| token.span).collect::<Vec<_>>(),
+                   tokens.iter().rev().map(|token| token.span).collect::<Vec<_>>());
+
+        for (view, token) in buffer.iter().zip(&tokens) {
+            assert_eq!(view.to_string(), token.to_string());
+        }
+    }
+
+    #[test]
+    fn views() {
+        let buffer: TokenBuffer = lex("x = \"y\" 1", LexerOptions::default()).into_iter().collect();
+
+        assert_eq!(buffer.get(0).unwrap().kind, TokenKindView::Identifier(Symbol::intern("x")));
+        assert_eq!(buffer.get(1).unwrap().kind, TokenKindView::Punctuator(Punctuator::EqualsSign));
+        assert!(matches!(buffer.get(2).unwrap().kind, TokenKindView::StringLiteral(literal) if literal.spelling == "\"y\""));
+        assert_eq!(buffer.get(3).unwrap().span, Span::detached(8, 9));
+        assert_eq!(buffer.get(4), None);
+    }
+
+    #[test]
+    fn file_of_tokens() {
+        let mut source_map = SourceMap::new();
+        source_map.add_file("a.c", "");
+        let file = source_map.add_file("b.c", "int b;");
+
+        let buffer: TokenBuffer = TokenStream::new(source_map.lexer(file, LexerOptions::default())).collect();
+        assert_eq!(buffer.file(), Some(file));
+        assert_eq!(buffer.get(1).unwrap().span, Span::new(file, 4, 5));
+        assert_eq!(TokenBuffer::new().file(), None);
+    }
+
+    #[test]
+    #[should_panic = "same file"]
+    fn tokens_of_other_file() {
+        let mut source_map = SourceMap::new();
+        let a = source_map.add_file("a.c", "a");
+        let b = source_map.add_file("b.c", "b");
+
+        let mut buffer = TokenBuffer::new();
+        buffer.extend(TokenStream::new(source_map.lexer(a, LexerOptions::default())));
+        buffer.extend(TokenStream::new(source_map.lexer(b, LexerOptions::default())));
+    }
+
+    #[test]
+    fn smaller_than_vec() {
+        let source_code = "static int f(int x) { return x * 2 + g(x, \"s\"); }\n".repeat(100);
+        let tokens = lex(&source_code, LexerOptions::default());
+
+        let mut buffer: TokenBuffer = tokens.iter().cloned().collect();
+        buffer.shrink_to_fit();
+
+        let vec_size = tokens.len() * size_of::<Token>();
+        assert!(buffer.allocated_size() * 3 < vec_size, "{} bytes is not much smaller than {vec_size}", buffer.allocated_size());
+    }
+}
@@ -0,0 +1,744 @@
+// Copyright (C) 2023 Tristan Gerritsen <tristan@thewoosh.org>
+// All Rights Reserved.
+
+use std::{
+    error::Error,
+    fmt::Display,
+    str::FromStr,
+};
+
+use crate::CharacterConstant;
+
+/// The widths of the standard integer types. These follow the LP64 data model
+/// used by Linux and macOS on 64-bit targets.
+const INT_WIDTH: u32 = 32;
+const LONG_WIDTH: u32 = 64;
+const LONG_LONG_WIDTH: u32 = 64;
+const SIZE_WIDTH: u32 = 64;
+
+/// The widest `_BitInt(N)` we can represent the value of.
+const BIT_INT_MAX_WIDTH: u32 = u128::BITS;
+
+/// A constant as described in ISO/IEC 9899 6.4.4.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Constant {
+    Integer(IntegerConstant),
+    Floating(FloatingConstant),
+    Character(CharacterConstant),
+}
+
+impl FromStr for Constant {
+    type Err = ConstantError;
+
+    /// Interprets the spelling of a pp-number as a constant.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (radix, body) = match s.get(..2) {
+            Some("0x" | "0X") => (Radix::Hexadecimal, &s[2..]),
+            Some("0b" | "0B") => (Radix::Binary, &s[2..]),
+            _ => (Radix::Decimal, s),
+        };
+
+        let whole_length = digit_sequence_length(body, radix);
+        let after_whole = &body[whole_length..];
+
+        let is_floating = match radix {
+            Radix::Hexadecimal => after_whole.starts_with(['.', 'p', 'P']),
+            Radix::Decimal => after_whole.starts_with(['.', 'e', 'E']),
+            _ => false,
+        };
+
+        if is_floating {
+            return FloatingConstant::parse(radix, body).map(Self::Floating);
+        }
+
+        IntegerConstant::parse(radix, &body[..whole_length], after_whole)
+            .map(Self::Integer)
+    }
+}
+
+/// Spells the constant as C, e.g. for dumps. Integer and floating constants
+/// are spelled from their value, so digit separators and the original
+/// spelling of the exponent aren't preserved.
+impl Display for Constant {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Integer(constant) => Display::fmt(constant, f),
+            Self::Floating(constant) => Display::fmt(constant, f),
+            Self::Character(constant) => Display::fmt(constant, f),
+        }
+    }
+}
+
+/// An integer constant as described in ISO/IEC 9899 6.4.4.1.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct IntegerConstant {
+    pub value: u128,
+    pub radix: Radix,
+    pub suffix: IntegerSuffix,
+    pub ty: IntegerType,
+}
+
+impl IntegerConstant {
+    fn parse(radix: Radix, digits: &str, suffix: &str) -> Result<Self, ConstantError> {
+        let radix = match radix {
+            Radix::Decimal if digits.starts_with('0') => Radix::Octal,
+            radix => radix,
+        };
+
+        let suffix: IntegerSuffix = suffix.parse()?;
+        let digits = remove_digit_separators(digits)?;
+        if digits.is_empty() {
+            return Err(ConstantError::MissingDigits);
+        }
+
+        let mut value: u128 = 0;
+        for char in digits.chars() {
+            let digit = char.to_digit(radix as u32)
+                .ok_or(ConstantError::InvalidDigit(char))?;
+
+            value = value.checked_mul(radix as u128)
+                .and_then(|value| value.checked_add(digit as u128))
+                .ok_or(ConstantError::Overflow)?;
+        }
+
+        let ty = IntegerType::for_value(value, radix, suffix)
+            .ok_or(ConstantError::Overflow)?;
+
+        Ok(Self {
+            value,
+            radix,
+            suffix,
+            ty,
+        })
+    }
+}
+
+impl Display for IntegerConstant {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.radix {
+            Radix::Binary => write!(f, "0b{:b}", self.value)?,
+            Radix::Octal if self.value == 0 => f.write_str("0")?,
+            Radix::Octal => write!(f, "0{:o}", self.value)?,
+            Radix::Decimal => write!(f, "{}", self.value)?,
+            Radix::Hexadecimal => write!(f, "0x{:x}", self.value)?,
+        }
+
+        Display::fmt(&self.suffix, f)
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Radix {
+    Binary = 2,
+    Octal = 8,
+    Decimal = 10,
+    Hexadecimal = 16,
+}
+
+/// The suffix of an integer constant, e.g. the `ull` of `1ull`.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct IntegerSuffix {
+    pub unsigned: bool,
+    pub length: Option<IntegerLength>,
+}
+
+impl FromStr for IntegerSuffix {
+    type Err = ConstantError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (mut unsigned, rest) = match s.strip_prefix(['u', 'U']) {
+            Some(rest) => (true, rest),
+            None => (false, s),
+        };
+
+        let rest = match rest.strip_suffix(['u', 'U']) {
+            Some(rest) if !unsigned => {
+                unsigned = true;
+                rest
+            }
+            _ => rest,
+        };
+
+        let length = match rest {
+            "" => None,
+            "l" | "L" => Some(IntegerLength::Long),
+            "ll" | "LL" => Some(IntegerLength::LongLong),
+            "z" | "Z" => Some(IntegerLength::Size),
+            "wb" | "WB" => Some(IntegerLength::BitPrecise),
+            _ => return Err(ConstantError::InvalidSuffix),
+        };
+
+        Ok(Self { unsigned, length })
+    }
+}
+
+impl Display for IntegerSuffix {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.unsigned {
+            f.write_str("u")?;
+        }
+
+        f.write_str(match self.length {
+            None => "",
+            Some(IntegerLength::Long) => "l",
+            Some(IntegerLength::LongLong) => "ll",
+            Some(IntegerLength::Size) => "z",
+            Some(IntegerLength::BitPrecise) => "wb",
+        })
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum IntegerLength {
+    /// `l` or `L`
+    Long,
+
+    /// `ll` or `LL`
+    LongLong,
+
+    /// `z` or `Z`
+    Size,
+
+    /// `wb` or `WB`
+    BitPrecise,
+}
+
+/// The type an integer constant gets, as specified in ISO/IEC 9899 6.4.4.1p6.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum IntegerType {
+    Int,
+    UnsignedInt,
+    Long,
+    UnsignedLong,
+    LongLong,
+    UnsignedLongLong,
+
+    /// The signed integer type corresponding to `size_t`.
+    SignedSize,
+    Size,
+
+    /// `_BitInt(N)`
+    BitInt(u32),
+
+    /// `unsigned _BitInt(N)`
+    UnsignedBitInt(u32),
+}
+
+impl IntegerType {
+    /// Picks the first type of the list in 6.4.4.1p6 that can represent the
+    /// value, or [`None`] if none of them can.
+    fn for_value(value: u128, radix: Radix, suffix: IntegerSuffix) -> Option<Self> {
+        use IntegerType::*;
+
+        let decimal = radix == Radix::Decimal;
+        let candidates: &[Self] = match (suffix.unsigned, suffix.length) {
+            (false, Some(IntegerLength::BitPrecise)) => {
+                let width = (u128::BITS - value.leading_zeros() + 1).max(2);
+                return (width <= BIT_INT_MAX_WIDTH).then_some(BitInt(width));
+            }
+            (true, Some(IntegerLength::BitPrecise)) => {
+                let width = (u128::BITS - value.leading_zeros()).max(1);
+                return Some(UnsignedBitInt(width));
+            }
+
+            (false, None) if decimal => &[Int, Long, LongLong],
+            (false, None) => &[Int, UnsignedInt, Long, UnsignedLong, LongLong, UnsignedLongLong],
+            (true, None) => &[UnsignedInt, UnsignedLong, UnsignedLongLong],
+
+            (false, Some(IntegerLength::Long)) if decimal => &[Long, LongLong],
+            (false, Some(IntegerLength::Long)) => &[Long, UnsignedLong, LongLong, UnsignedLongLong],
+            (true, Some(IntegerLength::Long)) => &[UnsignedLong, UnsignedLongLong],
+
+            (false, Some(IntegerLength::LongLong)) if decimal => &[LongLong],
+            (false, Some(IntegerLength::LongLong)) => &[LongLong, UnsignedLongLong],
+            (true, Some(IntegerLength::LongLong)) => &[UnsignedLongLong],
+
+            (false, Some(IntegerLength::Size)) => &[SignedSize],
+            (true, Some(IntegerLength::Size)) => &[Size],
+        };
+
+        candidates.iter()
+            .copied()
+            .find(|ty| ty.can_represent(value))
+    }
+
+    fn can_represent(&self, value: u128) -> bool {
+        let (width, signed) = match *self {
+            Self::Int => (INT_WIDTH, true),
+            Self::UnsignedInt => (INT_WIDTH, false),
+            Self::Long => (LONG_WIDTH, true),
+            Self::UnsignedLong => (LONG_WIDTH, false),
+            Self::LongLong => (LONG_LONG_WIDTH, true),
+            Self::UnsignedLongLong => (LONG_LONG_WIDTH, false),
+            Self::SignedSize => (SIZE_WIDTH, true),
+            Self::Size => (SIZE_WIDTH, false),
+            Self::BitInt(width) => (width, true),
+            Self::UnsignedBitInt(width) => (width, false),
+        };
+
+        let value_bits = if signed { width - 1 } else { width };
+        u128::BITS - value.leading_zeros() <= value_bits
+    }
+}
+
+/// A floating constant as described in ISO/IEC 9899 6.4.4.2.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct FloatingConstant {
+    pub value: f64,
+    pub radix: Radix,
+    pub suffix: FloatingSuffix,
+}
+
+impl FloatingConstant {
+    /// Parses the constant after the `0x` prefix is stripped off.
+    fn parse(radix: Radix, body: &str) -> Result<Self, ConstantError> {
+        let whole_length = digit_sequence_length(body, radix);
+        let whole = remove_digit_separators(&body[..whole_length])?;
+        let mut rest = &body[whole_length..];
+
+        let mut fraction = String::new();
+        if let Some(after_dot) = rest.strip_prefix('.') {
+            let fraction_length = digit_sequence_length(after_dot, radix);
+            fraction = remove_digit_separators(&after_dot[..fraction_length])?;
+            rest = &after_dot[fraction_length..];
+        }
+
+        if whole.is_empty() && fraction.is_empty() {
+            return Err(ConstantError::MissingDigits);
+        }
+
+        let exponent_chars: &[char] = match radix {
+            Radix::Hexadecimal => &['p', 'P'],
+            _ => &['e', 'E'],
+        };
+
+        let mut exponent = None;
+        if let Some(after_e) = rest.strip_prefix(exponent_chars) {
+            let sign_length = usize::from(after_e.starts_with(['+', '-']));
+            let digits_length = sign_length + digit_sequence_length(&after_e[sign_length..], Radix::Decimal);
+            let digits = remove_digit_separators(&after_e[sign_length..digits_length])?;
+            if digits.is_empty() {
+                return Err(ConstantError::MissingExponentDigits);
+            }
+
+            exponent = Some(format!("{}{digits}", &after_e[..sign_length]));
+            rest = &after_e[digits_length..];
+        }
+
+        let suffix = match rest {
+            "" => FloatingSuffix::None,
+            "f" | "F" => FloatingSuffix::Float,
+            "l" | "L" => FloatingSuffix::LongDouble,
+            _ => return Err(ConstantError::InvalidSuffix),
+        };
+
+        let value = match radix {
+            Radix::Hexadecimal => {
+                // The binary exponent is required for hexadecimal constants.
+                let exponent = exponent.ok_or(ConstantError::MissingExponentDigits)?;
+                let exponent = saturating_exponent(&exponent);
+
+                match suffix {
+                    FloatingSuffix::Float => {
+                        let bits = parse_hexadecimal_floating(&whole, &fraction, exponent, BinaryFormat::BINARY32)?;
+                        f32::from_bits(bits as u32) as f64
+                    }
+                    _ => f64::from_bits(parse_hexadecimal_floating(&whole, &fraction, exponent, BinaryFormat::BINARY64)?),
+                }
+            }
+            _ => {
+                let exponent = exponent.as_deref().unwrap_or("0");
+                let text = format!("{whole}.{fraction}e{exponent}");
+
+                // Rounding the `f64` again would round twice.
+                match suffix {
+                    FloatingSuffix::Float => text.parse::<f32>().map_err(|_| ConstantError::MissingDigits)? as f64,
+                    _ => text.parse().map_err(|_| ConstantError::MissingDigits)?,
+                }
+            }
+        };
+
+        if value.is_infinite() {
+            return Err(ConstantError::Overflow);
+        }
+
+        Ok(Self {
+            value,
+            radix,
+            suffix,
+        })
+    }
+}
+
+/// The parameters of an IEEE 754 binary interchange format, as named in the
+/// `<float.h>` macros of ISO/IEC 9899 5.2.4.2.2.
+#[derive(Copy, Clone, Debug)]
+struct BinaryFormat {
+    mantissa_digits: u32,
+    min_exp: i32,
+    max_exp: i32,
+}
+
+impl BinaryFormat {
+    /// `float`
+    const BINARY32: Self = Self {
+        mantissa_digits: f32::MANTISSA_DIGITS,
+        min_exp: f32::MIN_EXP,
+        max_exp: f32::MAX_EXP,
+    };
+
+    /// `double`, which is used for `long double` as well.
+    const BINARY64: Self = Self {
+        mantissa_digits: f64::MANTISSA_DIGITS,
+        min_exp: f64::MIN_EXP,
+        max_exp: f64::MAX_EXP,
+    };
+}
+
+/// The magnitude at which binary exponents are saturated, far beyond the
+/// exponents of any format, so that the digits of the mantissa can't bring
+/// them back in range.
+const MAX_BINARY_EXPONENT: i64 = 100_000;
+
+/// Parses the optionally signed decimal exponent, saturating it at
+/// [`MAX_BINARY_EXPONENT`], as huge exponents only make the value zero or
+/// overflow.
+fn saturating_exponent(exponent: &str) -> i64 {
+    let (negative, digits) = match exponent.strip_prefix('-') {
+        Some(digits) => (true, digits),
+        None => (false, exponent.strip_prefix('+').unwrap_or(exponent)),
+    };
+
+    let magnitude = digits.bytes()
+        .fold(0, |magnitude, digit| (magnitude * 10 + (digit - b'0') as i64).min(MAX_BINARY_EXPONENT));
+
+    match negative {
+        true => -magnitude,
+        false => magnitude,
+    }
+}
+
+/// Computes `0x{whole}.{fraction}p{exponent}`, rounded once to the nearest
+/// value of the format, ties to even, and returns its bits. The digits are
+/// collected into an integer, where the digits that don't fit are only
+/// remembered as a sticky bit, and the result is assembled from its bits, so
+/// that subnormal values don't underflow.
+fn parse_hexadecimal_floating(whole: &str, fraction: &str, exponent: i64, format: BinaryFormat) -> Result<u64, ConstantError> {
+    let fraction_bits = format.mantissa_digits - 1;
+
+    let mut mantissa = 0u64;
+    let mut exponent = exponent - 4 * fraction.len() as i64;
+    let mut sticky = false;
+    for char in whole.chars().chain(fraction.chars()) {
+        let digit = char.to_digit(16).ok_or(ConstantError::InvalidDigit(char))?;
+        if mantissa >> 60 == 0 {
+            mantissa = mantissa * 16 + digit as u64;
+        } else {
+            exponent += 4;
+            sticky |= digit != 0;
+        }
+    }
+
+    if mantissa == 0 {
+        return Ok(0);
+    }
+
+    // Normalize to `1.xxx * 2^exponent`, with the leading one in bit 63.
+    let leading_zeros = mantissa.leading_zeros();
+    let mantissa = (mantissa << leading_zeros) as u128;
+    let mut exponent = exponent + 63 - leading_zeros as i64;
+
+    let maximum_exponent = format.max_exp as i64 - 1;
+    if exponent > maximum_exponent {
+        return Err(ConstantError::Overflow);
+    }
+
+    // Subnormal values keep fewer bits, down to none at all for values that
+    // might still round up to the smallest subnormal value.
+    let minimum_exponent = format.min_exp as i64 - 1;
+    let kept_bits = match exponent < minimum_exponent {
+        true => format.mantissa_digits as i64 - (minimum_exponent - exponent),
+        false => format.mantissa_digits as i64,
+    };
+
+    if kept_bits < 0 {
+        return Ok(0);
+    }
+
+    let shift = 64 - kept_bits as u32;
+    let half = 1u128 << (shift - 1);
+    let remainder = mantissa & ((1 << shift) - 1);
+    let mut bits = (mantissa >> shift) as u64;
+    if remainder > half || (remainder == half && (sticky || bits & 1 == 1)) {
+        bits += 1;
+    }
+
+    if exponent < minimum_exponent {
+        // Rounding up to the implicit bit yields the smallest normal value.
+        return Ok(bits);
+    }
+
+    if bits == 1 << format.mantissa_digits {
+        bits >>= 1;
+        exponent += 1;
+        if exponent > maximum_exponent {
+            return Err(ConstantError::Overflow);
+        }
+    }
+
+    let biased_exponent = (exponent + maximum_exponent) as u64;
+    Ok(biased_exponent << fraction_bits | (bits & ((1 << fraction_bits) - 1)))
+}
+
+impl Display for FloatingConstant {
+    /// Spells the value in its radix, with the digits needed to parse back to
+    /// the same value.
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.radix {
+            Radix::Hexadecimal => write_hexadecimal_floating(self.value, f)?,
+
+            // The `Debug` representation always has a `.` or exponent, so
+            // that it isn't an integer constant. A `float` is spelled with
+            // the digits needed for its own precision.
+            _ if self.suffix == FloatingSuffix::Float => write!(f, "{:?}", self.value as f32)?,
+            _ => write!(f, "{:?}", self.value)?,
+        }
+
+        f.write_str(match self.suffix {
+            FloatingSuffix::None => "",
+            FloatingSuffix::Float => "f",
+            FloatingSuffix::LongDouble => "L",
+        })
+    }
+}
+
+/// Writes the value like `printf("%a")` does, e.g. `0x1.8p+1` for `3.0`.
+fn write_hexadecimal_floating(value: f64, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    const FRACTION_BITS: u32 = f64::MANTISSA_DIGITS - 1;
+
+    let bits = value.to_bits();
+    let fraction = bits & ((1 << FRACTION_BITS) - 1);
+    let biased_exponent = (bits >> FRACTION_BITS) as i32;
+
+    let (leading, exponent) = match (biased_exponent, fraction) {
+        (0, 0) => (0, 0),
+        (0, _) => (0, f64::MIN_EXP - 1),
+        _ => (1, biased_exponent - f64::MAX_EXP + 1),
+    };
+
+    write!(f, "0x{leading}")?;
+    if fraction != 0 {
+        let digits = format!("{fraction:013x}");
+        write!(f, ".{}", digits.trim_end_matches('0'))?;
+    }
+
+    write!(f, "p{exponent:+}")
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum FloatingSuffix {
+    /// No suffix, the constant has type `double`.
+    None,
+
+    /// `f` or `F`
+    Float,
+
+    /// `l` or `L`
+    LongDouble,
+}
+
+/// The length of the digits and digit separators at the start of `str`.
+fn digit_sequence_length(str: &str, radix: Radix) -> usize {
+    // Octal and binary are checked later, as `09.5` is a valid floating
+    // constant.
+    let radix = match radix {
+        Radix::Hexadecimal => 16,
+        _ => 10,
+    };
+
+    str.find(|char: char| char != '\'' && !char.is_digit(radix))
+        .unwrap_or(str.len())
+}
+
+/// Removes the C23 digit separators (6.4.4.1), which may only appear between
+/// two digits.
+fn remove_digit_separators(digits: &str) -> Result<String, ConstantError> {
+    if digits.starts_with('\'') || digits.ends_with('\'') || digits.contains("''") {
+        return Err(ConstantError::InvalidDigitSeparator);
+    }
+
+    Ok(digits.chars().filter(|char| *char != '\'').collect())
+}
+
+/// The reason a pp-number isn't a valid [`Constant`].
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum ConstantError {
+    /// A digit that is not valid for the radix, e.g. the `8` in `08`.
+    InvalidDigit(char),
+
+    /// A digit separator that isn't between two digits, e.g. `1''0`.
+    InvalidDigitSeparator,
+
+    /// The prefix or mantissa isn't followed by any digit, e.g. `0x`.
+    MissingDigits,
+
+    /// The exponent doesn't contain any digit, e.g. `1e+`.
+    MissingExponentDigits,
+
+    /// The suffix isn't one of those in 6.4.4.1 or 6.4.4.2.
+    InvalidSuffix,
+
+    /// The value doesn't fit in any type it is allowed to have.
+    Overflow,
+}
+
+impl Display for ConstantError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::InvalidDigit(char) => write!(f, "invalid digit '{char}' in constant"),
+            Self::InvalidDigitSeparator => f.write_str("digit separator must be between two digits"),
+            Self::MissingDigits => f.write_str("constant has no digits"),
+            Self::MissingExponentDigits => f.write_str("exponent has no digits"),
+            Self::InvalidSuffix => f.write_str("invalid suffix on constant"),
+            Self::Overflow => f.write_str("constant is too large for its type"),
+        }
+    }
+}
+
+impl Error for ConstantError {
+}
+
+#[cfg(test)]
+mod tests {
+    use rstest::rstest;
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[rstest]
+    #[case("0", 0, Radix::Octal, IntegerType::Int)]
+    #[case("42", 42, Radix::Decimal, IntegerType::Int)]
+    #[case("017", 15, Radix::Octal, IntegerType::Int)]
+    #[case("0x1F", 31, Radix::Hexadecimal, IntegerType::Int)]
+    #[case("0b101", 5, Radix::Binary, IntegerType::Int)]
+    #[case("1'000'000", 1_000_000, Radix::Decimal, IntegerType::Int)]
+    #[case("2147483647", 2147483647, Radix::Decimal, IntegerType::Int)]
+    #[case("2147483648", 2147483648, Radix::Decimal, IntegerType::Long)]
+    #[case("0x80000000", 0x80000000, Radix::Hexadecimal, IntegerType::UnsignedInt)]
+    #[case("0xFFFFFFFFFFFFFFFF", u64::MAX as u128, Radix::Hexadecimal, IntegerType::UnsignedLong)]
+    #[case("1u", 1, Radix::Decimal, IntegerType::UnsignedInt)]
+    #[case("1L", 1, Radix::Decimal, IntegerType::Long)]
+    #[case("1ul", 1, Radix::Decimal, IntegerType::UnsignedLong)]
+    #[case("1LU", 1, Radix::Decimal, IntegerType::UnsignedLong)]
+    #[case("1ll", 1, Radix::Decimal, IntegerType::LongLong)]
+    #[case("1ULL", 1, Radix::Decimal, IntegerType::UnsignedLongLong)]
+    #[case("1z", 1, Radix::Decimal, IntegerType::SignedSize)]
+    #[case("1uz", 1, Radix::Decimal, IntegerType::Size)]
+    #[case("1wb", 1, Radix::Decimal, IntegerType::BitInt(2))]
+    #[case("255wb", 255, Radix::Decimal, IntegerType::BitInt(9))]
+    #[case("255uwb", 255, Radix::Decimal, IntegerType::UnsignedBitInt(8))]
+    #[case("0uwb", 0, Radix::Octal, IntegerType::UnsignedBitInt(1))]
+    fn integer(
+        #[case] input: &str,
+        #[case] value: u128,
+        #[case] radix: Radix,
+        #[case] ty: IntegerType,
+    ) {
+        let Ok(Constant::Integer(constant)) = input.parse() else {
+            panic!("not an integer constant: {input}");
+        };
+
+        assert_eq!(constant.value, value);
+        assert_eq!(constant.radix, radix);
+        assert_eq!(constant.ty, ty);
+    }
+
+    #[rstest]
+    #[case("1.0", 1.0, Radix::Decimal, FloatingSuffix::None)]
+    #[case(".5", 0.5, Radix::Decimal, FloatingSuffix::None)]
+    #[case("5.", 5.0, Radix::Decimal, FloatingSuffix::None)]
+    #[case("1e3", 1000.0, Radix::Decimal, FloatingSuffix::None)]
+    #[case("1.5e-1f", 0.15f32 as f64, Radix::Decimal, FloatingSuffix::Float)]
+    #[case("2E+2L", 200.0, Radix::Decimal, FloatingSuffix::LongDouble)]
+    #[case("09.5", 9.5, Radix::Decimal, FloatingSuffix::None)]
+    #[case("1'0.0'1", 10.01, Radix::Decimal, FloatingSuffix::None)]
+    #[case("0x1p4", 16.0, Radix::Hexadecimal, FloatingSuffix::None)]
+    #[case("0x1.8p1", 3.0, Radix::Hexadecimal, FloatingSuffix::None)]
+    #[case("0X.8P-1f", 0.25, Radix::Hexadecimal, FloatingSuffix::Float)]
+    #[case("0x1p-99999999999", 0.0, Radix::Hexadecimal, FloatingSuffix::None)]
+    #[case("0x0p99999999999", 0.0, Radix::Hexadecimal, FloatingSuffix::None)]
+    #[case("0x1p-99999999999999999999f", 0.0, Radix::Hexadecimal, FloatingSuffix::Float)]
+    fn floating(
+        #[case] input: &str,
+        #[case] value: f64,
+        #[case] radix: Radix,
+        #[case] suffix: FloatingSuffix,
+    ) {
+        assert_eq!(input.parse(), Ok(Constant::Floating(FloatingConstant {
+            value,
+            radix,
+            suffix,
+        })));
+    }
+
+    #[rstest]
+    #[case("08", ConstantError::InvalidDigit('8'))]
+    #[case("0b102", ConstantError::InvalidDigit('2'))]
+    #[case("0x", ConstantError::MissingDigits)]
+    #[case("1''0", ConstantError::InvalidDigitSeparator)]
+    #[case("0x'1", ConstantError::InvalidDigitSeparator)]
+    #[case("1e", ConstantError::MissingExponentDigits)]
+    #[case("1e+", ConstantError::MissingExponentDigits)]
+    #[case("0x1.0", ConstantError::MissingExponentDigits)]
+    #[case("1lL", ConstantError::InvalidSuffix)]
+    #[case("1uu", ConstantError::InvalidSuffix)]
+    #[case("1f", ConstantError::InvalidSuffix)]
+    #[case("1.0x", ConstantError::InvalidSuffix)]
+    #[case("18446744073709551616", ConstantError::Overflow)]
+    #[case("0xFFFFFFFFFFFFFFFFF", ConstantError::Overflow)]
+    #[case("1e999", ConstantError::Overflow)]
+    #[case("1e39f", ConstantError::Overflow)]
+    #[case("0x1p128f", ConstantError::Overflow)]
+    #[case("0x1.ffffffp127f", ConstantError::Overflow)]
+    #[case("0x1p1024", ConstantError::Overflow)]
+    #[case("0x1p99999999999", ConstantError::Overflow)]
+    #[case("0x0.0001p+100001", ConstantError::Overflow)]
+    #[case("0x1.fffffffffffff8p1023", ConstantError::Overflow)]
+    fn invalid(#[case] input: &str, #[case] expected: ConstantError) {
+        assert_eq!(input.parse::<Constant>(), Err(expected));
+    }
+
+    #[rstest]
+    #[case("0", "0")]
+    #[case("42", "42")]
+    #[case("017", "017")]
+    #[case("0x1F", "0x1f")]
+    #[case("0B101", "0b101")]
+    #[case("1'000'000", "1000000")]
+    #[case("10uLL", "10ull")]
+    #[case("7LU", "7ul")]
+    #[case("3wb", "3wb")]
+    #[case("1.5", "1.5")]
+    #[case("1e3", "1000.0")]
+    #[case("1.5e-7f", "1.5e-7f")]
+    #[case("0.1f", "0.1f")]
+    #[case("0x1.000001p0f", "0x1p+0f")]
+    #[case("0x1.000003p0f", "0x1.000004p+0f")]
+    #[case("0x1p-149f", "0x1p-149f")]
+    #[case("3.4028235e38f", "3.4028235e38f")]
+    #[case("0x1p-2L", "0x1p-2L")]
+    #[case("0x1.8p1", "0x1.8p+1")]
+    #[case("0x.1p0", "0x1p-4")]
+    #[case("0x0p0", "0x0p+0")]
+    #[case("0x1.fffffffffffffp1023", "0x1.fffffffffffffp+1023")]
+    #[case("0x1p-1074", "0x0.0000000000001p-1022")]
+    #[case("0x1.0p-1074", "0x0.0000000000001p-1022")]
+    #[case("0x1p-1030", "0x0.01p-1022")]
+    #[case("0x1.0000000000000p-1022", "0x1p-1022")]
+    #[case("0x123456789abcdef0123p0", "0x1.23456789abcdfp+72")]
+    #[case("2.", "2.0")]
+    fn display(#[case] input: &str, #[case] expected: &str) {
+        let constant: Constant = input.parse().unwrap();
+        assert_eq!(constant.to_string(), expected);
+        assert_eq!(expected.parse(), Ok(constant));
+    }
+}
@@ -3,29 +3,383 @@
 
 use std::{
     error::Error,
-    fmt::Display, str::FromStr,
+    fmt::Display,
+    str::FromStr,
 };
 
-/// These can be found in appendix A.1.2
-#[derive(Copy, Clone, Debug, PartialEq, Eq)]
-#[derive(strum::AsRefStr, strum::EnumIter)]
+use crate::{
+    Dialect,
+    LanguageStandard,
+};
+
+/// The keywords of C89 up to C23 (6.4.1 of each standard), the keywords of
+/// C++20 that C doesn't have, and the keywords of compiler extensions. Which
+/// of them are keywords depends on the language, see
+/// [`Keyword::is_keyword_in`], [`Keyword::is_keyword_in_cpp`] and
+/// [`Keyword::is_keyword_in_dialect`].
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[derive(strum::AsRefStr, strum::EnumCount, strum::EnumIter, strum::FromRepr, strum::IntoStaticStr)]
 #[strum(serialize_all = "snake_case")]
+#[repr(u8)]
 pub enum Keyword {
-    Bool,
+    // C89
+    Auto,
+    Break,
+    Case,
     Char,
+    Const,
+    Continue,
+    Default,
+    Do,
+    Double,
+    Else,
+    Enum,
+    Extern,
+    Float,
+    For,
+    Goto,
+    If,
     Int,
+    Long,
+    Register,
+    Return,
+    Short,
+    Signed,
+    Sizeof,
+    Static,
+    Struct,
+    Switch,
+    Typedef,
+    Union,
+    Unsigned,
     Void,
+    Volatile,
+    While,
+
+    // C99
+    Inline,
+    Restrict,
+    #[strum(serialize = "_Bool")]
+    UnderscoreBool,
+    #[strum(serialize = "_Complex")]
+    UnderscoreComplex,
+    #[strum(serialize = "_Imaginary")]
+    UnderscoreImaginary,
+
+    // C11
+    #[strum(serialize = "_Alignas")]
+    UnderscoreAlignas,
+    #[strum(serialize = "_Alignof")]
+    UnderscoreAlignof,
+    #[strum(serialize = "_Atomic")]
+    UnderscoreAtomic,
+    #[strum(serialize = "_Generic")]
+    UnderscoreGeneric,
+    #[strum(serialize = "_Noreturn")]
+    UnderscoreNoreturn,
+    #[strum(serialize = "_Static_assert")]
+    UnderscoreStaticAssert,
+    #[strum(serialize = "_Thread_local")]
+    UnderscoreThreadLocal,
+
+    // C23
+    Alignas,
+    Alignof,
+    Bool,
+    Constexpr,
+    False,
+    Nullptr,
+    StaticAssert,
+    ThreadLocal,
+    True,
+    Typeof,
+    TypeofUnqual,
+    #[strum(serialize = "_BitInt")]
+    UnderscoreBitInt,
+    #[strum(serialize = "_Decimal32")]
+    UnderscoreDecimal32,
+    #[strum(serialize = "_Decimal64")]
+    UnderscoreDecimal64,
+    #[strum(serialize = "_Decimal128")]
+    UnderscoreDecimal128,
+
+    // C++20, without those that are keywords in C as well
+    Asm,
+    Catch,
+    #[strum(serialize = "char8_t")]
+    Char8T,
+    #[strum(serialize = "char16_t")]
+    Char16T,
+    #[strum(serialize = "char32_t")]
+    Char32T,
+    Class,
+    CoAwait,
+    CoReturn,
+    CoYield,
+    Concept,
+    ConstCast,
+    Consteval,
+    Constinit,
+    Decltype,
+    Delete,
+    DynamicCast,
+    Explicit,
+    Export,
+    Friend,
+    Mutable,
+    Namespace,
+    New,
+    Noexcept,
+    Operator,
+    Private,
+    Protected,
+    Public,
+    ReinterpretCast,
+    Requires,
+    StaticCast,
+    Template,
+    This,
+    Throw,
+    Try,
+    Typeid,
+    Typename,
+    Using,
+    Virtual,
+    #[strum(serialize = "wchar_t")]
+    WcharT,
+
+    // Extensions, see Dialect
+    #[strum(serialize = "__asm__")]
+    GnuAsm,
+    #[strum(serialize = "__attribute__")]
+    GnuAttribute,
+    #[strum(serialize = "__extension__")]
+    GnuExtension,
+    #[strum(serialize = "__inline__")]
+    GnuInline,
+    #[strum(serialize = "__int128")]
+    GnuInt128,
+    #[strum(serialize = "__restrict")]
+    GnuRestrict,
+    #[strum(serialize = "__declspec")]
+    MsvcDeclspec,
+}
+
+/// Every keyword sorted by spelling, so [`Keyword::from_str`] can use a binary
+/// search instead of comparing against every keyword.
+const KEYWORDS_BY_SPELLING: [(&str, Keyword); 105] = [
+    ("_Alignas", Keyword::UnderscoreAlignas),
+    ("_Alignof", Keyword::UnderscoreAlignof),
+    ("_Atomic", Keyword::UnderscoreAtomic),
+    ("_BitInt", Keyword::UnderscoreBitInt),
+    ("_Bool", Keyword::UnderscoreBool),
+    ("_Complex", Keyword::UnderscoreComplex),
+    ("_Decimal128", Keyword::UnderscoreDecimal128),
+    ("_Decimal32", Keyword::UnderscoreDecimal32),
+    ("_Decimal64", Keyword::UnderscoreDecimal64),
+    ("_Generic", Keyword::UnderscoreGeneric),
+    ("_Imaginary", Keyword::UnderscoreImaginary),
+    ("_Noreturn", Keyword::UnderscoreNoreturn),
+    ("_Static_assert", Keyword::UnderscoreStaticAssert),
+    ("_Thread_local", Keyword::UnderscoreThreadLocal),
+    ("__asm__", Keyword::GnuAsm),
+    ("__attribute__", Keyword::GnuAttribute),
+    ("__declspec", Keyword::MsvcDeclspec),
+    ("__extension__", Keyword::GnuExtension),
+    ("__inline__", Keyword::GnuInline),
+    ("__int128", Keyword::GnuInt128),
+    ("__restrict", Keyword::GnuRestrict),
+    ("alignas", Keyword::Alignas),
+    ("alignof", Keyword::Alignof),
+    ("asm", Keyword::Asm),
+    ("auto", Keyword::Auto),
+    ("bool", Keyword::Bool),
+    ("break", Keyword::Break),
+    ("case", Keyword::Case),
+    ("catch", Keyword::Catch),
+    ("char", Keyword::Char),
+    ("char16_t", Keyword::Char16T),
+    ("char32_t", Keyword::Char32T),
+    ("char8_t", Keyword::Char8T),
+    ("class", Keyword::Class),
+    ("co_await", Keyword::CoAwait),
+    ("co_return", Keyword::CoReturn),
+    ("co_yield", Keyword::CoYield),
+    ("concept", Keyword::Concept),
+    ("const", Keyword::Const),
+    ("const_cast", Keyword::ConstCast),
+    ("consteval", Keyword::Consteval),
+    ("constexpr", Keyword::Constexpr),
+    ("constinit", Keyword::Constinit),
+    ("continue", Keyword::Continue),
+    ("decltype", Keyword::Decltype),
+    ("default", Keyword::Default),
+    ("delete", Keyword::Delete),
+    ("do", Keyword::Do),
+    ("double", Keyword::Double),
+    ("dynamic_cast", Keyword::DynamicCast),
+    ("else", Keyword::Else),
+    ("enum", Keyword::Enum),
+    ("explicit", Keyword::Explicit),
+    ("export", Keyword::Export),
+    ("extern", Keyword::Extern),
+    ("false", Keyword::False),
+    ("float", Keyword::Float),
+    ("for", Keyword::For),
+    ("friend", Keyword::Friend),
+    ("goto", Keyword::Goto),
+    ("if", Keyword::If),
+    ("inline", Keyword::Inline),
+    ("int", Keyword::Int),
+    ("long", Keyword::Long),
+    ("mutable", Keyword::Mutable),
+    ("namespace", Keyword::Namespace),
+    ("new", Keyword::New),
+    ("noexcept", Keyword::Noexcept),
+    ("nullptr", Keyword::Nullptr),
+    ("operator", Keyword::Operator),
+    ("private", Keyword::Private),
+    ("protected", Keyword::Protected),
+    ("public", Keyword::Public),
+    ("register", Keyword::Register),
+    ("reinterpret_cast", Keyword::ReinterpretCast),
+    ("requires", Keyword::Requires),
+    ("restrict", Keyword::Restrict),
+    ("return", Keyword::Return),
+    ("short", Keyword::Short),
+    ("signed", Keyword::Signed),
+    ("sizeof", Keyword::Sizeof),
+    ("static", Keyword::Static),
+    ("static_assert", Keyword::StaticAssert),
+    ("static_cast", Keyword::StaticCast),
+    ("struct", Keyword::Struct),
+    ("switch", Keyword::Switch),
+    ("template", Keyword::Template),
+    ("this", Keyword::This),
+    ("thread_local", Keyword::ThreadLocal),
+    ("throw", Keyword::Throw),
+    ("true", Keyword::True),
+    ("try", Keyword::Try),
+    ("typedef", Keyword::Typedef),
+    ("typeid", Keyword::Typeid),
+    ("typename", Keyword::Typename),
+    ("typeof", Keyword::Typeof),
+    ("typeof_unqual", Keyword::TypeofUnqual),
+    ("union", Keyword::Union),
+    ("unsigned", Keyword::Unsigned),
+    ("using", Keyword::Using),
+    ("virtual", Keyword::Virtual),
+    ("void", Keyword::Void),
+    ("volatile", Keyword::Volatile),
+    ("wchar_t", Keyword::WcharT),
+    ("while", Keyword::While),
+];
+
+impl Keyword {
+    /// The first standard of C in which this is a keyword, or [`None`] if it
+    /// is only a keyword in C++ or an extension.
+    pub const fn introduced_in(&self) -> Option<LanguageStandard> {
+        if self.is_cpp_only() || self.is_extension() {
+            return None;
+        }
+
+        Some(match self {
+            Self::Inline | Self::Restrict | Self::UnderscoreBool | Self::UnderscoreComplex
+                | Self::UnderscoreImaginary => LanguageStandard::C99,
+
+            Self::UnderscoreAlignas | Self::UnderscoreAlignof | Self::UnderscoreAtomic
+                | Self::UnderscoreGeneric | Self::UnderscoreNoreturn
+                | Self::UnderscoreStaticAssert | Self::UnderscoreThreadLocal => LanguageStandard::C11,
+
+            Self::Alignas | Self::Alignof | Self::Bool | Self::Constexpr | Self::False
+                | Self::Nullptr | Self::StaticAssert | Self::ThreadLocal | Self::True
+                | Self::Typeof | Self::TypeofUnqual | Self::UnderscoreBitInt
+                | Self::UnderscoreDecimal32 | Self::UnderscoreDecimal64
+                | Self::UnderscoreDecimal128 => LanguageStandard::C23,
+
+            _ => LanguageStandard::C89,
+        })
+    }
+
+    /// Whether this identifier is a keyword in the given standard of C.
+    pub fn is_keyword_in(&self, standard: LanguageStandard) -> bool {
+        self.introduced_in().is_some_and(|introduced_in| introduced_in <= standard)
+    }
+
+    /// Whether this identifier is a keyword in C++20. The keywords of C that
+    /// start with an underscore and a capital letter aren't, nor are
+    /// `restrict` and `typeof`.
+    pub const fn is_keyword_in_cpp(&self) -> bool {
+        !matches!(self, Self::Restrict | Self::Typeof | Self::TypeofUnqual)
+            && !self.is_c_underscore_keyword()
+            && !self.is_extension()
+    }
+
+    /// Whether this is a keyword of a compiler extension, which are keywords
+    /// in both C and C++ when the [`Dialect`] enables them.
+    pub const fn is_extension(&self) -> bool {
+        matches!(self,
+            Self::GnuAsm | Self::GnuAttribute | Self::GnuExtension | Self::GnuInline | Self::GnuInt128
+                | Self::GnuRestrict | Self::MsvcDeclspec)
+    }
+
+    /// Whether the extensions of the dialect make this a keyword. This is
+    /// always false for the keywords of ISO C and C++.
+    pub const fn is_keyword_in_dialect(&self, dialect: Dialect) -> bool {
+        match self {
+            Self::GnuRestrict => dialect.gnu || dialect.msvc,
+            Self::MsvcDeclspec => dialect.msvc,
+            _ => self.is_extension() && dialect.gnu,
+        }
+    }
+
+    const fn is_cpp_only(&self) -> bool {
+        (*self as u8) >= (Self::Asm as u8) && !self.is_extension()
+    }
+
+    const fn is_c_underscore_keyword(&self) -> bool {
+        matches!(self,
+            Self::UnderscoreBool | Self::UnderscoreComplex | Self::UnderscoreImaginary
+                | Self::UnderscoreAlignas | Self::UnderscoreAlignof | Self::UnderscoreAtomic
+                | Self::UnderscoreGeneric | Self::UnderscoreNoreturn | Self::UnderscoreStaticAssert
+                | Self::UnderscoreThreadLocal | Self::UnderscoreBitInt | Self::UnderscoreDecimal32
+                | Self::UnderscoreDecimal64 | Self::UnderscoreDecimal128)
+    }
+
+    /// C23 (6.4.1p2) added alternate spellings for some of the keywords that
+    /// start with an underscore and a capital letter. This maps both
+    /// spellings to the C23 one, for consumers that don't care about the
+    /// spelling. The extension spellings of standard keywords, like
+    /// `__inline__`, are mapped as well.
+    pub const fn canonical(self) -> Self {
+        match self {
+            Self::GnuAsm => Self::Asm,
+            Self::GnuInline => Self::Inline,
+            Self::GnuRestrict => Self::Restrict,
+            Self::UnderscoreAlignas => Self::Alignas,
+            Self::UnderscoreAlignof => Self::Alignof,
+            Self::UnderscoreBool => Self::Bool,
+            Self::UnderscoreStaticAssert => Self::StaticAssert,
+            Self::UnderscoreThreadLocal => Self::ThreadLocal,
+            _ => self,
+        }
+    }
+}
+
+impl Display for Keyword {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_ref())
+    }
 }
 
 impl FromStr for Keyword {
     type Err = IdentifierIsNotAKeyword;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        use strum::IntoEnumIterator;
-
-        Self::iter()
-            .find(|x| x.as_ref() == s)
-            .ok_or(IdentifierIsNotAKeyword)
+        KEYWORDS_BY_SPELLING
+            .binary_search_by(|(spelling, _)| (*spelling).cmp(s))
+            .map(|index| KEYWORDS_BY_SPELLING[index].1)
+            .map_err(|_| IdentifierIsNotAKeyword)
     }
 }
 
@@ -40,3 +394,77 @@ impl Display for IdentifierIsNotAKeyword {
 
 impl Error for IdentifierIsNotAKeyword {
 }
+
+#[cfg(test)]
+mod tests {
+    use rstest::rstest;
+    use strum::IntoEnumIterator;
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn table_is_sorted_and_complete() {
+        assert!(KEYWORDS_BY_SPELLING.windows(2).all(|pair| pair[0].0 < pair[1].0));
+
+        for keyword in Keyword::iter() {
+            assert_eq!(keyword.as_ref().parse(), Ok(keyword));
+        }
+    }
+
+    #[rstest]
+    #[case("return", Ok(Keyword::Return))]
+    #[case("_Bool", Ok(Keyword::UnderscoreBool))]
+    #[case("_Static_assert", Ok(Keyword::UnderscoreStaticAssert))]
+    #[case("typeof_unqual", Ok(Keyword::TypeofUnqual))]
+    #[case("_BitInt", Ok(Keyword::UnderscoreBitInt))]
+    #[case("char8_t", Ok(Keyword::Char8T))]
+    #[case("co_await", Ok(Keyword::CoAwait))]
+    #[case("reinterpret_cast", Ok(Keyword::ReinterpretCast))]
+    #[case("__attribute__", Ok(Keyword::GnuAttribute))]
+    #[case("__declspec", Ok(Keyword::MsvcDeclspec))]
+    #[case("main", Err(IdentifierIsNotAKeyword))]
+    #[case("Return", Err(IdentifierIsNotAKeyword))]
+    #[case("_bool", Err(IdentifierIsNotAKeyword))]
+    #[case("", Err(IdentifierIsNotAKeyword))]
+    fn from_str(#[case] input: &str, #[case] expected: Result<Keyword, IdentifierIsNotAKeyword>) {
+        assert_eq!(input.parse(), expected);
+    }
+
+    #[rstest]
+    #[case(Keyword::Int, LanguageStandard::C89, true)]
+    #[case(Keyword::Restrict, LanguageStandard::C89, false)]
+    #[case(Keyword::Restrict, LanguageStandard::C99, true)]
+    #[case(Keyword::UnderscoreBool, LanguageStandard::C99, true)]
+    #[case(Keyword::Bool, LanguageStandard::C17, false)]
+    #[case(Keyword::Bool, LanguageStandard::C23, true)]
+    #[case(Keyword::UnderscoreThreadLocal, LanguageStandard::C11, true)]
+    #[case(Keyword::UnderscoreThreadLocal, LanguageStandard::C99, false)]
+    #[case(Keyword::Class, LanguageStandard::C23, false)]
+    #[case(Keyword::GnuInt128, LanguageStandard::C23, false)]
+    fn is_keyword_in(#[case] keyword: Keyword, #[case] standard: LanguageStandard, #[case] expected: bool) {
+        assert_eq!(keyword.is_keyword_in(standard), expected);
+    }
+
+    #[rstest]
+    #[case(Keyword::Int, true)]
+    #[case(Keyword::Bool, true)]
+    #[case(Keyword::Nullptr, true)]
+    #[case(Keyword::Class, true)]
+    #[case(Keyword::WcharT, true)]
+    #[case(Keyword::UnderscoreBool, false)]
+    #[case(Keyword::Restrict, false)]
+    #[case(Keyword::TypeofUnqual, false)]
+    #[case(Keyword::GnuAsm, false)]
+    fn is_keyword_in_cpp(#[case] keyword: Keyword, #[case] expected: bool) {
+        assert_eq!(keyword.is_keyword_in_cpp(), expected);
+    }
+
+    #[test]
+    fn canonical() {
+        assert_eq!(Keyword::UnderscoreBool.canonical(), Keyword::Bool);
+        assert_eq!(Keyword::Bool.canonical(), Keyword::Bool);
+        assert_eq!(Keyword::UnderscoreBitInt.canonical(), Keyword::UnderscoreBitInt);
+        assert_eq!(Keyword::GnuInline.canonical(), Keyword::Inline);
+        assert_eq!(Keyword::GnuAttribute.canonical(), Keyword::GnuAttribute);
+    }
+}
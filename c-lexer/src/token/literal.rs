@@ -0,0 +1,414 @@
+// Copyright (C) 2023 Tristan Gerritsen <tristan@thewoosh.org>
+// All Rights Reserved.
+
+use std::{
+    error::Error,
+    fmt::Display,
+    str::FromStr,
+};
+
+/// The encoding prefix of a character constant or string literal, as
+/// described in ISO/IEC 9899 6.4.4.5 and 6.4.5.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Encoding {
+    /// No prefix, the code units are the UTF-8 bytes.
+    None,
+
+    /// `u8`
+    Utf8,
+
+    /// `u`
+    Utf16,
+
+    /// `U`
+    Utf32,
+
+    /// `L`, the code units are the UTF-32 code points of a 32-bit `wchar_t`.
+    Wide,
+}
+
+impl Encoding {
+    /// Splits the encoding prefix off the spelling of a literal.
+    pub fn split_prefix(spelling: &str) -> (Self, &str) {
+        const PREFIXES: [(&str, Encoding); 4] = [
+            ("u8", Encoding::Utf8),
+            ("u", Encoding::Utf16),
+            ("U", Encoding::Utf32),
+            ("L", Encoding::Wide),
+        ];
+
+        PREFIXES.iter()
+            .find_map(|(prefix, encoding)| {
+                spelling.strip_prefix(prefix).map(|rest| (*encoding, rest))
+            })
+            .unwrap_or((Self::None, spelling))
+    }
+
+    /// The largest value a single code unit can hold.
+    pub const fn max_code_unit(&self) -> u32 {
+        match self {
+            Self::None | Self::Utf8 => u8::MAX as u32,
+            Self::Utf16 => u16::MAX as u32,
+            Self::Utf32 | Self::Wide => u32::MAX,
+        }
+    }
+
+    fn push_char(&self, char: char, code_units: &mut Vec<u32>) {
+        match self {
+            Self::None | Self::Utf8 => {
+                let mut buffer = [0; 4];
+                code_units.extend(char.encode_utf8(&mut buffer).bytes().map(u32::from));
+            }
+            Self::Utf16 => {
+                let mut buffer = [0; 2];
+                code_units.extend(char.encode_utf16(&mut buffer).iter().map(|unit| *unit as u32));
+            }
+            Self::Utf32 | Self::Wide => code_units.push(char as u32),
+        }
+    }
+}
+
+/// A character constant as described in ISO/IEC 9899 6.4.4.5.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CharacterConstant {
+    pub encoding: Encoding,
+    pub code_units: Vec<u32>,
+
+    /// The original spelling, including the prefix and quotes.
+    pub spelling: String,
+}
+
+impl CharacterConstant {
+    /// The value of the constant. For multi-character constants without a
+    /// prefix, the code units are packed the way GCC and Clang do it.
+    pub fn value(&self) -> u32 {
+        self.code_units.iter()
+            .fold(0, |value, unit| value.wrapping_shl(8) | unit)
+    }
+}
+
+impl Display for CharacterConstant {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.spelling)
+    }
+}
+
+impl FromStr for CharacterConstant {
+    type Err = LiteralError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (encoding, code_units) = decode(s, '\'')?;
+
+        if code_units.is_empty() {
+            return Err(LiteralError::EmptyCharacterConstant);
+        }
+
+        if encoding != Encoding::None && code_units.len() > 1 {
+            return Err(LiteralError::TooManyCodeUnits);
+        }
+
+        Ok(Self {
+            encoding,
+            code_units,
+            spelling: s.into(),
+        })
+    }
+}
+
+/// A string literal as described in ISO/IEC 9899 6.4.5.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct StringLiteral {
+    pub encoding: Encoding,
+
+    /// The code units, excluding the terminating null character.
+    pub code_units: Vec<u32>,
+
+    /// The original spelling, including the prefix and quotes.
+    pub spelling: String,
+}
+
+impl Display for StringLiteral {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.spelling)
+    }
+}
+
+impl FromStr for StringLiteral {
+    type Err = LiteralError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (encoding, code_units) = decode(s, '"')?;
+        Ok(Self {
+            encoding,
+            code_units,
+            spelling: s.into(),
+        })
+    }
+}
+
+/// Decodes the escape sequences (6.4.4.5) of a literal into code units.
+fn decode(spelling: &str, quote: char) -> Result<(Encoding, Vec<u32>), LiteralError> {
+    let (encoding, rest) = Encoding::split_prefix(spelling);
+    if quote == '"' {
+        if let Some(raw) = rest.strip_prefix("R\"") {
+            return decode_raw(encoding, raw).map(|code_units| (encoding, code_units));
+        }
+    }
+
+    let mut chars = rest.strip_prefix(quote)
+        .ok_or(LiteralError::Unterminated)?
+        .chars()
+        .peekable();
+
+    let mut code_units = Vec::new();
+
+    loop {
+        match chars.next() {
+            None => return Err(LiteralError::Unterminated),
+            Some(char) if char == quote => break,
+            Some('\\') => (),
+            Some(char) => {
+                encoding.push_char(char, &mut code_units);
+                continue;
+            }
+        }
+
+        let Some(escape) = chars.next() else {
+            return Err(LiteralError::Unterminated);
+        };
+
+        let simple = match escape {
+            '\'' | '"' | '?' | '\\' => Some(escape as u32),
+            'a' => Some(0x07),
+            'b' => Some(0x08),
+            'f' => Some(0x0C),
+            'n' => Some(0x0A),
+            'r' => Some(0x0D),
+            't' => Some(0x09),
+            'v' => Some(0x0B),
+            _ => None,
+        };
+
+        if let Some(unit) = simple {
+            code_units.push(unit);
+            continue;
+        }
+
+        match escape {
+            '0'..='7' => {
+                let mut value = escape.to_digit(8).unwrap_or_default();
+                for _ in 0..2 {
+                    let Some(digit) = chars.peek().and_then(|char| char.to_digit(8)) else {
+                        break;
+                    };
+
+                    value = value * 8 + digit;
+                    _ = chars.next();
+                }
+
+                if value > encoding.max_code_unit() {
+                    return Err(LiteralError::OctalEscapeOutOfRange);
+                }
+
+                code_units.push(value);
+            }
+
+            'x' => {
+                let mut value: Option<u32> = None;
+                while let Some(digit) = chars.peek().and_then(|char| char.to_digit(16)) {
+                    _ = chars.next();
+                    value = Some(value.unwrap_or_default()
+                        .checked_mul(16)
+                        .and_then(|value| value.checked_add(digit))
+                        .ok_or(LiteralError::HexEscapeOutOfRange)?);
+                }
+
+                let value = value.ok_or(LiteralError::MissingHexDigits)?;
+                if value > encoding.max_code_unit() {
+                    return Err(LiteralError::HexEscapeOutOfRange);
+                }
+
+                code_units.push(value);
+            }
+
+            'u' | 'U' => {
+                let length = if escape == 'u' { 4 } else { 8 };
+
+                let mut value = 0;
+                for _ in 0..length {
+                    let digit = chars.next()
+                        .and_then(|char| char.to_digit(16))
+                        .ok_or(LiteralError::InvalidUniversalCharacterName)?;
+                    value = value * 16 + digit;
+                }
+
+                let char = char::from_u32(value)
+                    .ok_or(LiteralError::InvalidUniversalCharacterName)?;
+                encoding.push_char(char, &mut code_units);
+            }
+
+            _ => return Err(LiteralError::InvalidEscape(escape)),
+        }
+    }
+
+    if chars.next().is_some() {
+        return Err(LiteralError::Unterminated);
+    }
+
+    Ok((encoding, code_units))
+}
+
+/// Decodes the characters of a C++ raw string literal, `raw` being the
+/// spelling after the `R"`. Escape sequences aren't recognized in raw
+/// literals.
+fn decode_raw(encoding: Encoding, raw: &str) -> Result<Vec<u32>, LiteralError> {
+    let (delimiter, body) = raw.split_once('(').ok_or(LiteralError::Unterminated)?;
+    let terminator = format!("){delimiter}\"");
+    let body = body.strip_suffix(&terminator).ok_or(LiteralError::Unterminated)?;
+
+    let mut code_units = Vec::new();
+    for char in body.chars() {
+        encoding.push_char(char, &mut code_units);
+    }
+
+    Ok(code_units)
+}
+
+/// The reason a character constant or string literal couldn't be decoded.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum LiteralError {
+    /// The literal isn't closed by a matching quote.
+    Unterminated,
+
+    /// A `\` followed by a character that doesn't form an escape sequence.
+    InvalidEscape(char),
+
+    /// A `\x` that isn't followed by any hexadecimal digit.
+    MissingHexDigits,
+
+    /// The value of a `\x` escape doesn't fit in a code unit.
+    HexEscapeOutOfRange,
+
+    /// The value of an octal escape doesn't fit in a code unit.
+    OctalEscapeOutOfRange,
+
+    /// A `\u` or `\U` with too few digits, or one that doesn't name a
+    /// Unicode scalar value.
+    InvalidUniversalCharacterName,
+
+    /// `''`
+    EmptyCharacterConstant,
+
+    /// A character constant with an encoding prefix that doesn't fit in a
+    /// single code unit.
+    TooManyCodeUnits,
+}
+
+impl Display for LiteralError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Unterminated => f.write_str("missing terminating quote"),
+            Self::InvalidEscape(char) => write!(f, "unknown escape sequence '\\{char}'"),
+            Self::MissingHexDigits => f.write_str("\\x used with no following hex digits"),
+            Self::HexEscapeOutOfRange => f.write_str("hex escape sequence out of range"),
+            Self::OctalEscapeOutOfRange => f.write_str("octal escape sequence out of range"),
+            Self::InvalidUniversalCharacterName => f.write_str("invalid universal character name"),
+            Self::EmptyCharacterConstant => f.write_str("empty character constant"),
+            Self::TooManyCodeUnits => f.write_str("character too large for enclosing character literal type"),
+        }
+    }
+}
+
+impl Error for LiteralError {
+}
+
+#[cfg(test)]
+mod tests {
+    use rstest::rstest;
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[rstest]
+    #[case("'a'", Encoding::None, &[0x61])]
+    #[case("'\\n'", Encoding::None, &[0x0A])]
+    #[case("'\\''", Encoding::None, &[0x27])]
+    #[case("'\\0'", Encoding::None, &[0])]
+    #[case("'\\377'", Encoding::None, &[0xFF])]
+    #[case("'\\x41'", Encoding::None, &[0x41])]
+    #[case("'ab'", Encoding::None, &[0x61, 0x62])]
+    #[case("'é'", Encoding::None, &[0xC3, 0xA9])]
+    #[case("u8'a'", Encoding::Utf8, &[0x61])]
+    #[case("u'é'", Encoding::Utf16, &[0xE9])]
+    #[case("U'\\U0001F600'", Encoding::Utf32, &[0x1F600])]
+    #[case("L'\\x12345678'", Encoding::Wide, &[0x12345678])]
+    fn character_constant(
+        #[case] input: &str,
+        #[case] encoding: Encoding,
+        #[case] code_units: &[u32],
+    ) {
+        assert_eq!(input.parse(), Ok(CharacterConstant {
+            encoding,
+            code_units: code_units.to_vec(),
+            spelling: input.into(),
+        }));
+    }
+
+    #[test]
+    fn multi_character_constant_value() {
+        let constant: CharacterConstant = "'ab'".parse().unwrap();
+        assert_eq!(constant.value(), 0x6162);
+    }
+
+    #[rstest]
+    #[case("\"\"", Encoding::None, &[])]
+    #[case("\"hi\\tthere\"", Encoding::None, &[0x68, 0x69, 0x09, 0x74, 0x68, 0x65, 0x72, 0x65])]
+    #[case("\"\\u00e9\"", Encoding::None, &[0xC3, 0xA9])]
+    #[case("u8\"\\u00e9\"", Encoding::Utf8, &[0xC3, 0xA9])]
+    #[case("u\"\\U0001F600\"", Encoding::Utf16, &[0xD83D, 0xDE00])]
+    #[case("U\"\\U0001F600\"", Encoding::Utf32, &[0x1F600])]
+    #[case("L\"a\\0b\"", Encoding::Wide, &[0x61, 0, 0x62])]
+    #[case("\"\\1234\"", Encoding::None, &[0o123, 0x34])]
+    #[case("\"\\\"\\?\\\\\"", Encoding::None, &[0x22, 0x3F, 0x5C])]
+    #[case("R\"(a\\n)\"", Encoding::None, &[0x61, 0x5C, 0x6E])]
+    #[case("R\"x()\")x\"", Encoding::None, &[0x29, 0x22])]
+    #[case("u8R\"(\n)\"", Encoding::Utf8, &[0x0A])]
+    #[case("LR\"--(é)--\"", Encoding::Wide, &[0xE9])]
+    fn string_literal(
+        #[case] input: &str,
+        #[case] encoding: Encoding,
+        #[case] code_units: &[u32],
+    ) {
+        assert_eq!(input.parse(), Ok(StringLiteral {
+            encoding,
+            code_units: code_units.to_vec(),
+            spelling: input.into(),
+        }));
+    }
+
+    #[rstest]
+    #[case("\"abc", LiteralError::Unterminated)]
+    #[case("\"abc\\\"", LiteralError::Unterminated)]
+    #[case("\"\\q\"", LiteralError::InvalidEscape('q'))]
+    #[case("\"\\x\"", LiteralError::MissingHexDigits)]
+    #[case("\"\\x100\"", LiteralError::HexEscapeOutOfRange)]
+    #[case("u\"\\x10000\"", LiteralError::HexEscapeOutOfRange)]
+    #[case("U\"\\x100000000\"", LiteralError::HexEscapeOutOfRange)]
+    #[case("\"\\777\"", LiteralError::OctalEscapeOutOfRange)]
+    #[case("\"\\u12\"", LiteralError::InvalidUniversalCharacterName)]
+    #[case("\"\\uD800\"", LiteralError::InvalidUniversalCharacterName)]
+    #[case("\"\\U00110000\"", LiteralError::InvalidUniversalCharacterName)]
+    #[case("R\"x(a)\"", LiteralError::Unterminated)]
+    #[case("R\"a\"", LiteralError::Unterminated)]
+    fn invalid_string_literal(#[case] input: &str, #[case] expected: LiteralError) {
+        assert_eq!(input.parse::<StringLiteral>(), Err(expected));
+    }
+
+    #[rstest]
+    #[case("''", LiteralError::EmptyCharacterConstant)]
+    #[case("'a", LiteralError::Unterminated)]
+    #[case("u8'é'", LiteralError::TooManyCodeUnits)]
+    #[case("U'ab'", LiteralError::TooManyCodeUnits)]
+    fn invalid_character_constant(#[case] input: &str, #[case] expected: LiteralError) {
+        assert_eq!(input.parse::<CharacterConstant>(), Err(expected));
+    }
+}
@@ -1,35 +1,98 @@
 // Copyright (C) 2023 Tristan Gerritsen <tristan@thewoosh.org>
 // All Rights Reserved.
 
+mod buffer;
+mod constant;
 mod keyword;
+mod literal;
 mod preprocessor;
 mod punctuator;
 
+use std::fmt::Display;
+
 pub use self::{
+    buffer::{
+        TokenBuffer,
+        TokenBufferIter,
+        TokenKindView,
+        TokenView,
+    },
+    constant::{
+        Constant,
+        ConstantError,
+        FloatingConstant,
+        FloatingSuffix,
+        IntegerConstant,
+        IntegerLength,
+        IntegerSuffix,
+        IntegerType,
+        Radix,
+    },
     keyword::{
         IdentifierIsNotAKeyword,
         Keyword,
     },
+    literal::{
+        CharacterConstant,
+        Encoding,
+        LiteralError,
+        StringLiteral,
+    },
     preprocessor::{
+        HeaderName,
+        HeaderNameForm,
         PreprocessorToken,
         PreprocessorTokenKind,
         PreprocessorTokenNotMappedToTokenError,
+        TokenFlags,
+    },
+    punctuator::{
+        Punctuator,
+        UnknownPunctuator,
     },
-    punctuator::Punctuator,
 };
 
-#[derive(Debug, PartialEq)]
+use crate::{
+    Span,
+    Symbol,
+};
+
+#[derive(Clone, Debug, PartialEq)]
 pub struct Token {
     pub kind: TokenKind,
-    pub start: usize,
-    pub end: usize,
+    pub span: Span,
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Clone, Debug, PartialEq)]
 pub enum TokenKind {
     Keyword(Keyword),
-    Identifier(String),
-    Constant,
-    StringLiteral(String),
+    Identifier(Symbol),
+    Constant(Constant),
+    StringLiteral(StringLiteral),
     Punctuator(Punctuator),
+
+    /// A C++ literal followed by a ud-suffix, e.g. `"abc"_s` or `12_km`, see
+    /// C++ \[lex.ext\].
+    UserDefinedLiteral(Box<TokenKind>, Symbol),
+}
+
+/// Spells the token as C.
+impl Display for Token {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        Display::fmt(&self.kind, f)
+    }
+}
+
+/// Spells the token as C.
+impl Display for TokenKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Keyword(keyword) => Display::fmt(keyword, f),
+            Self::Identifier(symbol) => Display::fmt(symbol, f),
+            Self::Constant(constant) => Display::fmt(constant, f),
+            Self::StringLiteral(literal) => Display::fmt(literal, f),
+            Self::Punctuator(punctuator) => Display::fmt(punctuator, f),
+            Self::UserDefinedLiteral(literal, suffix) => write!(f, "{literal}{suffix}"),
+        }
+    }
 }
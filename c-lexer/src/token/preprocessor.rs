@@ -2,85 +2,491 @@
 // All Rights Reserved.
 
 use std::{
+    borrow::Cow,
     error::Error,
     fmt::Display,
 };
 
 use crate::{
-    IdentifierIsNotAKeyword,
+    Constant,
+    ConstantError,
+    Diagnostic,
+    DiagnosticKind,
+    LexerOptions,
+    LiteralError,
     Punctuator,
+    Span,
+    Symbol,
     Token,
     TokenKind,
 };
 
-#[derive(Debug, PartialEq)]
-pub struct PreprocessorToken {
-    pub kind: PreprocessorTokenKind,
-    pub start: usize,
-    pub end: usize,
+#[derive(Clone, Debug, PartialEq)]
+pub struct PreprocessorToken<'source_code> {
+    pub kind: PreprocessorTokenKind<'source_code>,
+    pub span: Span,
+    pub flags: TokenFlags,
 }
 
-impl TryFrom<PreprocessorToken> for Token {
-    type Error = PreprocessorTokenNotMappedToTokenError;
+/// What precedes a [`PreprocessorToken`], as the whitespace and comments
+/// themselves are only part of the token stream in
+/// [trivia mode][crate::LexerOptions::trivia].
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct TokenFlags {
+    /// Only whitespace and comments precede the token on its line, which is
+    /// where a `#` starts a directive.
+    pub at_start_of_line: bool,
+
+    /// Whitespace or a comment directly precedes the token, which matters for
+    /// e.g. telling `#define f(x)` and `#define f (x)` apart.
+    pub has_leading_whitespace: bool,
+}
 
-    fn try_from(value: PreprocessorToken) -> Result<Self, Self::Error> {
-        Ok(Self {
-            kind: value.kind.try_into()?,
-            start: value.start,
-            end: value.end,
+impl TokenFlags {
+    /// The flags of a token at the very start of the source code.
+    pub const START_OF_FILE: Self = Self {
+        at_start_of_line: true,
+        has_leading_whitespace: false,
+    };
+}
+
+impl PreprocessorToken<'_> {
+    /// Converts to a [`Token`], where the options decide which identifiers
+    /// are keywords.
+    pub fn into_token(self, options: &LexerOptions) -> Result<Token, PreprocessorTokenNotMappedToTokenError> {
+        Ok(Token {
+            kind: self.kind.into_token_kind(options)?,
+            span: self.span,
         })
     }
+
+    /// Copies the payload out of the source code, e.g. to keep the token
+    /// around after the source code is dropped.
+    pub fn into_owned(self) -> PreprocessorToken<'static> {
+        PreprocessorToken {
+            kind: self.kind.into_owned(),
+            span: self.span,
+            flags: self.flags,
+        }
+    }
 }
 
-#[derive(Debug, PartialEq)]
-pub enum PreprocessorTokenKind {
-    Whitespace(char),
-    HeaderName,
-    Identifier(String),
-    PpNumber,
-    CharacterConstant,
-    StringLiteral(String),
+/// Spells the token as C, without the whitespace before it.
+impl Display for PreprocessorToken<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        Display::fmt(&self.kind, f)
+    }
+}
+
+impl TryFrom<PreprocessorToken<'_>> for Token {
+    type Error = PreprocessorTokenNotMappedToTokenError;
+
+    fn try_from(value: PreprocessorToken<'_>) -> Result<Self, Self::Error> {
+        value.into_token(&LexerOptions::default())
+    }
+}
+
+/// The payloads borrow from the source code, unless line splices or trigraphs
+/// had to be removed from their spelling. Tokens that don't stem from the
+/// source code, e.g. those synthesized by macro expansion, use the owned
+/// form.
+#[derive(Clone, Debug, PartialEq)]
+#[derive(strum::IntoStaticStr)]
+#[strum(serialize_all = "snake_case")]
+pub enum PreprocessorTokenKind<'source_code> {
+    /// A run of white-space characters, which is only produced in
+    /// [trivia mode][crate::LexerOptions::trivia].
+    Whitespace(Cow<'source_code, str>),
+    HeaderName(HeaderName),
+    Identifier(Symbol),
+    /// A preprocessing number (ISO/IEC 9899 6.4.8), with line splices
+    /// removed.
+    PpNumber(Cow<'source_code, str>),
+    /// A character constant (ISO/IEC 9899 6.4.4.5) as spelled in the source,
+    /// including the encoding prefix and quotes, with line splices removed.
+    CharacterConstant(Cow<'source_code, str>),
+
+    /// A string literal (ISO/IEC 9899 6.4.5) as spelled in the source,
+    /// including the encoding prefix and quotes, with line splices removed.
+    StringLiteral(Cow<'source_code, str>),
     Punctuator(Punctuator),
 
-    /// ?
-    OtherUniversalCharacterName,
+    /// A punctuator spelled using one of the alternative spellings of
+    /// ISO/IEC 9899 6.4.6p3, e.g. `<:` for `[`. It behaves exactly like the
+    /// [`PreprocessorTokenKind::Punctuator`], except for its spelling.
+    Digraph(Punctuator),
+
+    /// A comment (ISO/IEC 9899 6.4.9), including the `//` or `/*` and `*/`
+    /// delimiters, with line splices removed. Only produced in
+    /// [trivia mode][crate::LexerOptions::trivia].
+    Comment(Cow<'source_code, str>),
+
+    /// A universal character name (e.g. `\u00A0`) of a character that can't
+    /// be part of an identifier.
+    OtherUniversalCharacterName(char),
 
     /// >
     NonWhiteSpaceCharacter(char),
 }
 
-impl TryFrom<PreprocessorTokenKind> for TokenKind {
-    type Error = PreprocessorTokenNotMappedToTokenError;
+impl PreprocessorTokenKind<'_> {
+    /// A stable, machine-readable name for the kind of token, e.g.
+    /// `pp_number`.
+    pub fn name(&self) -> &'static str {
+        self.into()
+    }
+
+    /// The punctuator, regardless of whether it was spelled as a digraph.
+    pub const fn punctuator(&self) -> Option<Punctuator> {
+        match self {
+            Self::Punctuator(punctuator) | Self::Digraph(punctuator) => Some(*punctuator),
+            _ => None,
+        }
+    }
+
+    /// Converts to a [`TokenKind`], where the options decide which identifiers
+    /// are keywords.
+    pub fn into_token_kind(self, options: &LexerOptions) -> Result<TokenKind, PreprocessorTokenNotMappedToTokenError> {
+        match self {
+            PreprocessorTokenKind::Whitespace(..) => Err(PreprocessorTokenNotMappedToTokenError::NoEquivalent),
+            PreprocessorTokenKind::HeaderName(..) => Err(PreprocessorTokenNotMappedToTokenError::NoEquivalent),
+            PreprocessorTokenKind::Identifier(symbol) => {
+                if let Some(punctuator) = options.alternative_token(symbol) {
+                    return Ok(TokenKind::Punctuator(punctuator));
+                }
 
-    fn try_from(value: PreprocessorTokenKind) -> Result<TokenKind, Self::Error> {
-        match value {
-            PreprocessorTokenKind::Whitespace(..) => Err(PreprocessorTokenNotMappedToTokenError),
-            PreprocessorTokenKind::HeaderName => Err(PreprocessorTokenNotMappedToTokenError),
-            PreprocessorTokenKind::Identifier(ident) => match ident.parse() {
-                Ok(keyword) => Ok(TokenKind::Keyword(keyword)),
-                Err(IdentifierIsNotAKeyword) => Ok(TokenKind::Identifier(ident)),
+                match options.keyword(symbol) {
+                    Some(keyword) => Ok(TokenKind::Keyword(keyword)),
+                    None => Ok(TokenKind::Identifier(symbol)),
+                }
+            }
+            PreprocessorTokenKind::PpNumber(pp_number) if options.is_cpp() => parse_cpp_number(&pp_number),
+            PreprocessorTokenKind::PpNumber(pp_number) => pp_number.parse()
+                .map(TokenKind::Constant)
+                .map_err(PreprocessorTokenNotMappedToTokenError::InvalidConstant),
+            PreprocessorTokenKind::CharacterConstant(spelling) => {
+                let (spelling, suffix) = split_ud_suffix(&spelling, '\'', options);
+                spelling.parse()
+                    .map(|constant| with_ud_suffix(TokenKind::Constant(Constant::Character(constant)), suffix))
+                    .map_err(PreprocessorTokenNotMappedToTokenError::InvalidLiteral)
+            }
+            PreprocessorTokenKind::StringLiteral(spelling) => {
+                let (spelling, suffix) = split_ud_suffix(&spelling, '"', options);
+                spelling.parse()
+                    .map(|literal| with_ud_suffix(TokenKind::StringLiteral(literal), suffix))
+                    .map_err(PreprocessorTokenNotMappedToTokenError::InvalidLiteral)
             }
-            PreprocessorTokenKind::PpNumber => todo!(),
-            PreprocessorTokenKind::CharacterConstant => todo!(),
-            PreprocessorTokenKind::StringLiteral(string_literal) => Ok(TokenKind::StringLiteral(string_literal)),
             PreprocessorTokenKind::Punctuator(punctuator) => Ok(TokenKind::Punctuator(punctuator)),
+            PreprocessorTokenKind::Digraph(punctuator) => Ok(TokenKind::Punctuator(punctuator)),
+            PreprocessorTokenKind::Comment(..) => Err(PreprocessorTokenNotMappedToTokenError::NoEquivalent),
+
+            PreprocessorTokenKind::OtherUniversalCharacterName(c) => Err(PreprocessorTokenNotMappedToTokenError::StrayCharacter(c)),
+            PreprocessorTokenKind::NonWhiteSpaceCharacter(c) => Err(PreprocessorTokenNotMappedToTokenError::StrayCharacter(c)),
+        }
+    }
+
+    pub fn into_owned(self) -> PreprocessorTokenKind<'static> {
+        match self {
+            Self::Whitespace(whitespace) => PreprocessorTokenKind::Whitespace(Cow::Owned(whitespace.into_owned())),
+            Self::HeaderName(header_name) => PreprocessorTokenKind::HeaderName(header_name),
+            Self::Identifier(symbol) => PreprocessorTokenKind::Identifier(symbol),
+            Self::PpNumber(spelling) => PreprocessorTokenKind::PpNumber(Cow::Owned(spelling.into_owned())),
+            Self::CharacterConstant(spelling) => PreprocessorTokenKind::CharacterConstant(Cow::Owned(spelling.into_owned())),
+            Self::StringLiteral(spelling) => PreprocessorTokenKind::StringLiteral(Cow::Owned(spelling.into_owned())),
+            Self::Punctuator(punctuator) => PreprocessorTokenKind::Punctuator(punctuator),
+            Self::Digraph(punctuator) => PreprocessorTokenKind::Digraph(punctuator),
+            Self::Comment(text) => PreprocessorTokenKind::Comment(Cow::Owned(text.into_owned())),
+            Self::OtherUniversalCharacterName(c) => PreprocessorTokenKind::OtherUniversalCharacterName(c),
+            Self::NonWhiteSpaceCharacter(c) => PreprocessorTokenKind::NonWhiteSpaceCharacter(c),
+        }
+    }
+}
+
+/// Spells the token as C, after translation phases 1 and 2.
+impl Display for PreprocessorTokenKind<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Whitespace(text) | Self::PpNumber(text) | Self::CharacterConstant(text)
+                | Self::StringLiteral(text) | Self::Comment(text) => f.write_str(text),
+            Self::HeaderName(header_name) => Display::fmt(header_name, f),
+            Self::Identifier(symbol) => Display::fmt(symbol, f),
+            Self::Punctuator(punctuator) => Display::fmt(punctuator, f),
+            Self::Digraph(punctuator) => f.write_str(punctuator.digraph().unwrap_or(punctuator.as_str())),
+            Self::OtherUniversalCharacterName(char) if (*char as u32) <= 0xFFFF => {
+                write!(f, "\\u{:04X}", *char as u32)
+            }
+            Self::OtherUniversalCharacterName(char) => write!(f, "\\U{:08X}", *char as u32),
+            Self::NonWhiteSpaceCharacter(char) => write!(f, "{char}"),
+        }
+    }
+}
+
+/// Parses a C++ pp-number, which is a user-defined literal (C++ \[lex.ext\])
+/// if it ends in an identifier that doesn't belong to the constant, e.g.
+/// `12_km` or `1ms`. The longest prefix that is a constant is taken.
+fn parse_cpp_number(pp_number: &str) -> Result<TokenKind, PreprocessorTokenNotMappedToTokenError> {
+    let error = match pp_number.parse() {
+        Ok(constant) => return Ok(TokenKind::Constant(constant)),
+        Err(error) => error,
+    };
+
+    pp_number.char_indices()
+        .rev()
+        .filter(|(index, _)| *index != 0 && is_ud_suffix(&pp_number[*index..]))
+        .find_map(|(index, _)| {
+            let constant = pp_number[..index].parse().ok()?;
+            Some(TokenKind::UserDefinedLiteral(Box::new(TokenKind::Constant(constant)), Symbol::intern(&pp_number[index..])))
+        })
+        .ok_or(PreprocessorTokenNotMappedToTokenError::InvalidConstant(error))
+}
+
+/// Splits the ud-suffix off the spelling of a C++ character constant or
+/// string literal, i.e. the identifier after the closing `quote`.
+fn split_ud_suffix<'spelling>(spelling: &'spelling str, quote: char, options: &LexerOptions) -> (&'spelling str, Option<Symbol>) {
+    if !options.is_cpp() {
+        return (spelling, None);
+    }
 
-            PreprocessorTokenKind::OtherUniversalCharacterName => todo!(),
-            PreprocessorTokenKind::NonWhiteSpaceCharacter(c) => todo!("unsupported char: {c} U+{:X}", c as u32),
+    match spelling.rfind(quote) {
+        Some(index) if is_ud_suffix(&spelling[index + 1..]) => {
+            (&spelling[..=index], Some(Symbol::intern(&spelling[index + 1..])))
         }
+        _ => (spelling, None),
     }
 }
 
+fn with_ud_suffix(literal: TokenKind, suffix: Option<Symbol>) -> TokenKind {
+    match suffix {
+        Some(suffix) => TokenKind::UserDefinedLiteral(Box::new(literal), suffix),
+        None => literal,
+    }
+}
+
+fn is_ud_suffix(text: &str) -> bool {
+    let mut chars = text.chars();
+    chars.next().is_some_and(|char| char == '_' || unicode_ident::is_xid_start(char))
+        && chars.all(unicode_ident::is_xid_continue)
+}
+
+impl TryFrom<PreprocessorTokenKind<'_>> for TokenKind {
+    type Error = PreprocessorTokenNotMappedToTokenError;
+
+    fn try_from(value: PreprocessorTokenKind<'_>) -> Result<TokenKind, Self::Error> {
+        value.into_token_kind(&LexerOptions::default())
+    }
+}
+
+/// The name of a header or resource in an `#include`, `#import` or `#embed`
+/// directive, as described in ISO/IEC 9899 6.4.7.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct HeaderName {
+    /// The characters between the delimiters, which are taken literally.
+    pub name: String,
+    pub form: HeaderNameForm,
+}
+
+impl Display for HeaderName {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.form {
+            HeaderNameForm::Angle => write!(f, "<{}>", self.name),
+            HeaderNameForm::Quote => write!(f, "\"{}\"", self.name),
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum HeaderNameForm {
+    /// `<stdio.h>`, searched for in the system include paths.
+    Angle,
+
+    /// `"foo.h"`, searched for relative to the current file first.
+    Quote,
+}
+
 /// Not every [`PreprocessorTokenKind`] can be converted to a [`TokenKind`], as
 /// specified in ISO/IEC 9899 6.4.
 #[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
-pub struct PreprocessorTokenNotMappedToTokenError;
+pub enum PreprocessorTokenNotMappedToTokenError {
+    /// The kind has no token equivalent, e.g. whitespace and comments.
+    NoEquivalent,
+
+    /// The pp-number isn't a valid integer or floating constant.
+    InvalidConstant(ConstantError),
+
+    /// The character constant or string literal contains an invalid escape
+    /// sequence, or isn't terminated.
+    InvalidLiteral(LiteralError),
+
+    /// A character that can't be part of any token, e.g. `@`.
+    StrayCharacter(char),
+}
+
+impl PreprocessorTokenNotMappedToTokenError {
+    /// The diagnostic to report for the token at the span that couldn't be
+    /// converted, or [`None`] if it is simply dropped, like whitespace.
+    pub fn to_diagnostic(&self, span: Span) -> Option<Diagnostic> {
+        let kind = match *self {
+            Self::NoEquivalent => return None,
+            Self::InvalidConstant(error) => DiagnosticKind::InvalidConstant(error),
+            Self::InvalidLiteral(error) => DiagnosticKind::InvalidLiteral(error),
+            Self::StrayCharacter(char) => DiagnosticKind::StrayCharacter(char),
+        };
+
+        Some(Diagnostic { kind, span })
+    }
+}
 
 impl Display for PreprocessorTokenNotMappedToTokenError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        f.write_str("PreprocessorTokenNotMappedToTokenError")
+        match self {
+            Self::NoEquivalent => f.write_str("PreprocessorTokenNotMappedToTokenError"),
+            Self::InvalidConstant(error) => Display::fmt(error, f),
+            Self::InvalidLiteral(error) => Display::fmt(error, f),
+            Self::StrayCharacter(char) => write!(f, "stray '{char}' in program"),
+        }
     }
 }
 
 impl Error for PreprocessorTokenNotMappedToTokenError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            Self::NoEquivalent => None,
+            Self::InvalidConstant(error) => Some(error),
+            Self::InvalidLiteral(error) => Some(error),
+            Self::StrayCharacter(..) => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rstest::rstest;
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[rstest]
+    #[case(PreprocessorTokenKind::Whitespace(" ".into()), None)]
+    #[case(PreprocessorTokenKind::Comment("// a".into()), None)]
+    #[case(PreprocessorTokenKind::NonWhiteSpaceCharacter('@'), Some(DiagnosticKind::StrayCharacter('@')))]
+    #[case(PreprocessorTokenKind::OtherUniversalCharacterName('\u{A0}'), Some(DiagnosticKind::StrayCharacter('\u{A0}')))]
+    #[case(PreprocessorTokenKind::PpNumber("0x".into()), Some(DiagnosticKind::InvalidConstant(ConstantError::MissingDigits)))]
+    fn unmapped_diagnostic(#[case] kind: PreprocessorTokenKind, #[case] expected: Option<DiagnosticKind>) {
+        let error = kind.into_token_kind(&LexerOptions::default()).unwrap_err();
+        let span = Span::detached(3, 4);
+        assert_eq!(error.to_diagnostic(span), expected.map(|kind| Diagnostic { kind, span }));
+    }
+
+    #[rstest]
+    #[case(PreprocessorTokenKind::Identifier("main".into()), "main")]
+    #[case(PreprocessorTokenKind::Punctuator(Punctuator::LeftBitShiftAssign), "<<=")]
+    #[case(PreprocessorTokenKind::Digraph(Punctuator::DoublePound), "%:%:")]
+    #[case(PreprocessorTokenKind::HeaderName(HeaderName { name: "stdio.h".into(), form: HeaderNameForm::Angle }), "<stdio.h>")]
+    #[case(PreprocessorTokenKind::HeaderName(HeaderName { name: "a.h".into(), form: HeaderNameForm::Quote }), "\"a.h\"")]
+    #[case(PreprocessorTokenKind::StringLiteral("u8\"a\\n\"".into()), "u8\"a\\n\"")]
+    #[case(PreprocessorTokenKind::OtherUniversalCharacterName('\u{A0}'), "\\u00A0")]
+    #[case(PreprocessorTokenKind::OtherUniversalCharacterName('\u{1F600}'), "\\U0001F600")]
+    #[case(PreprocessorTokenKind::NonWhiteSpaceCharacter('@'), "@")]
+    fn display(#[case] kind: PreprocessorTokenKind, #[case] expected: &str) {
+        assert_eq!(kind.to_string(), expected);
+    }
+
+    #[test]
+    fn display_round_trips_through_lexer() {
+        let input = "#include <a.h>\nint main(void) { return x[1] <: 0x1F :> + 'c' + u\"s\"; } @";
+        let kinds = |input: &str| -> Vec<PreprocessorTokenKind<'static>> {
+            crate::Lexer::new(input).map(|token| token.kind.into_owned()).collect()
+        };
+
+        let respelled = crate::Lexer::new(input)
+            .map(|token| {
+                let separator = if token.flags.at_start_of_line { "\n" } else { " " };
+                format!("{separator}{token}")
+            })
+            .collect::<String>();
+
+        assert_eq!(kinds(&respelled), kinds(input));
+    }
+
+    #[rstest]
+    #[case(PreprocessorTokenKind::PpNumber("1".into()), "pp_number")]
+    #[case(PreprocessorTokenKind::Digraph(Punctuator::Pound), "digraph")]
+    #[case(PreprocessorTokenKind::OtherUniversalCharacterName('\u{A0}'), "other_universal_character_name")]
+    fn name(#[case] kind: PreprocessorTokenKind, #[case] expected: &str) {
+        assert_eq!(kind.name(), expected);
+    }
+
+    #[rstest]
+    #[case("int", "int")]
+    #[case("_Bool", "_Bool")]
+    #[case("main", "main")]
+    #[case("0x10u", "0x10u")]
+    #[case("\"a\"", "\"a\"")]
+    #[case("'\\n'", "'\\n'")]
+    #[case("<:", "[")]
+    fn token_display(#[case] input: &str, #[case] expected: &str) {
+        let token = crate::Lexer::new(input).next().unwrap().into_token(&LexerOptions::default()).unwrap();
+        assert_eq!(token.to_string(), expected);
+    }
+
+    fn cpp_options() -> LexerOptions {
+        LexerOptions {
+            language: crate::Language::Cpp,
+            ..Default::default()
+        }
+    }
+
+    #[rstest]
+    #[case("12_km", "12", "_km")]
+    #[case("1ms", "1", "ms")]
+    #[case("0x1f_x", "0x1f", "_x")]
+    #[case("1.5_deg", "1.5", "_deg")]
+    #[case("1'000_n", "1000", "_n")]
+    #[case("\"abc\"s", "\"abc\"", "s")]
+    #[case("u8\"abc\"_s", "u8\"abc\"", "_s")]
+    #[case("'a'_c", "'a'", "_c")]
+    #[case("R\"(a\"b)\"_r", "R\"(a\"b)\"", "_r")]
+    fn user_defined_literal(#[case] input: &str, #[case] literal: &str, #[case] suffix: &str) {
+        let options = cpp_options();
+        let mut lexer = crate::Lexer::with_options(input, options.clone());
+        let kind = lexer.next().unwrap().kind.into_token_kind(&options).unwrap();
+        assert_eq!(lexer.next(), None);
+
+        let TokenKind::UserDefinedLiteral(actual_literal, actual_suffix) = kind else {
+            panic!("not a user-defined literal: {kind:?}");
+        };
+        assert_eq!(actual_literal.to_string(), literal);
+        assert_eq!(actual_suffix.as_str(), suffix);
+    }
+
+    #[rstest]
+    #[case("and", TokenKind::Punctuator(Punctuator::LogicalAnd))]
+    #[case("xor_eq", TokenKind::Punctuator(Punctuator::BitwiseXorAssign))]
+    #[case("class", TokenKind::Keyword(crate::Keyword::Class))]
+    #[case("_Bool", TokenKind::Identifier(Symbol::intern("_Bool")))]
+    #[case("10u", TokenKind::Constant("10u".parse().unwrap()))]
+    fn cpp_token_kind(#[case] input: &str, #[case] expected: TokenKind) {
+        let options = cpp_options();
+        let kind = crate::Lexer::with_options(input, options.clone()).next().unwrap().kind;
+        assert_eq!(kind.into_token_kind(&options), Ok(expected));
+    }
+
+    #[rstest]
+    #[case("and", TokenKind::Identifier(Symbol::intern("and")))]
+    #[case("class", TokenKind::Identifier(Symbol::intern("class")))]
+    fn cpp_token_kind_in_c(#[case] input: &str, #[case] expected: TokenKind) {
+        let kind = crate::Lexer::new(input).next().unwrap().kind;
+        assert_eq!(kind.into_token_kind(&LexerOptions::default()), Ok(expected));
+    }
+
+    #[test]
+    fn no_user_defined_literals_in_c() {
+        let kinds: Vec<_> = crate::Lexer::new("\"abc\"s").map(|token| token.kind).collect();
+        assert_eq!(kinds, [
+            PreprocessorTokenKind::StringLiteral("\"abc\"".into()),
+            PreprocessorTokenKind::Identifier(Symbol::intern("s")),
+        ]);
+
+        let error = PreprocessorTokenKind::PpNumber("12_km".into()).into_token_kind(&LexerOptions::default());
+        assert!(matches!(error, Err(PreprocessorTokenNotMappedToTokenError::InvalidConstant(..))));
+    }
 }
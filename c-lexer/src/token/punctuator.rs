@@ -1,7 +1,16 @@
 // Copyright (C) 2023 Tristan Gerritsen <tristan@thewoosh.org>
 // All Rights Reserved.
 
-#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+use std::{
+    error::Error,
+    fmt::Display,
+    str::FromStr,
+};
+
+/// These can be found in ISO/IEC 9899 6.4.6.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+#[derive(strum::EnumCount, strum::EnumIter, strum::FromRepr)]
+#[repr(u8)]
 pub enum Punctuator {
     LeftSquareBracket,
     RightSquareBracket,
@@ -15,6 +24,12 @@ pub enum Punctuator {
     FullStop,
     PointerMemberAccessOperator,
 
+    /// `.*`, only in C++.
+    PointerToMemberOperator,
+
+    /// `->*`, only in C++.
+    PointerToMemberPointerOperator,
+
     IncrementOperator,
     DecrementOperator,
 
@@ -38,6 +53,9 @@ pub enum Punctuator {
     EqualTo,
     NotEqualTo,
 
+    /// `<=>`, only in C++.
+    ThreeWayComparison,
+
     BitwiseXor,
     BitwiseOr,
 
@@ -67,3 +85,187 @@ pub enum Punctuator {
     Pound,
     DoublePound,
 }
+
+impl Punctuator {
+    /// The spelling of the punctuator in C.
+    pub const fn as_str(&self) -> &'static str {
+        match self {
+            Self::LeftSquareBracket => "[",
+            Self::RightSquareBracket => "]",
+            Self::LeftParenthesis => "(",
+            Self::RightParenthesis => ")",
+            Self::LeftCurlyBracket => "{",
+            Self::RightCurlyBracket => "}",
+            Self::FullStop => ".",
+            Self::PointerMemberAccessOperator => "->",
+            Self::PointerToMemberOperator => ".*",
+            Self::PointerToMemberPointerOperator => "->*",
+            Self::IncrementOperator => "++",
+            Self::DecrementOperator => "--",
+            Self::ReferenceOperatorOrBitwiseAnd => "&",
+            Self::Asterisk => "*",
+            Self::PlusSign => "+",
+            Self::Minus => "-",
+            Self::Tilde => "~",
+            Self::ExclamationMark => "!",
+            Self::Solidus => "/",
+            Self::Percentage => "%",
+            Self::LeftBitShift => "<<",
+            Self::RightBitShift => ">>",
+            Self::LessThan => "<",
+            Self::GreaterThan => ">",
+            Self::LessThanOrEqualTo => "<=",
+            Self::GreaterThanOrEqualTo => ">=",
+            Self::EqualTo => "==",
+            Self::NotEqualTo => "!=",
+            Self::ThreeWayComparison => "<=>",
+            Self::BitwiseXor => "^",
+            Self::BitwiseOr => "|",
+            Self::LogicalAnd => "&&",
+            Self::LogicalOr => "||",
+            Self::QuestionMark => "?",
+            Self::Colon => ":",
+            Self::DoubleColon => "::",
+            Self::Semicolon => ";",
+            Self::Ellipsis => "...",
+            Self::EqualsSign => "=",
+            Self::MultiplyAssign => "*=",
+            Self::DivideAssign => "/=",
+            Self::ModuloAssign => "%=",
+            Self::AddAssign => "+=",
+            Self::SubtractAssign => "-=",
+            Self::LeftBitShiftAssign => "<<=",
+            Self::RightBitShiftAssign => ">>=",
+            Self::BitwiseAndAssign => "&=",
+            Self::BitwiseXorAssign => "^=",
+            Self::BitwiseOrAssign => "|=",
+            Self::Comma => ",",
+            Self::Pound => "#",
+            Self::DoublePound => "##",
+        }
+    }
+
+    /// The alternative spelling of ISO/IEC 9899 6.4.6p3, if the punctuator
+    /// has one.
+    pub const fn digraph(&self) -> Option<&'static str> {
+        match self {
+            Self::LeftSquareBracket => Some("<:"),
+            Self::RightSquareBracket => Some(":>"),
+            Self::LeftCurlyBracket => Some("<%"),
+            Self::RightCurlyBracket => Some("%>"),
+            Self::Pound => Some("%:"),
+            Self::DoublePound => Some("%:%:"),
+            _ => None,
+        }
+    }
+}
+
+impl Display for Punctuator {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for Punctuator {
+    type Err = UnknownPunctuator;
+
+    /// Parses the spelling of a punctuator, including the digraphs.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        <Self as strum::IntoEnumIterator>::iter()
+            .find(|punctuator| punctuator.as_str() == s || punctuator.digraph() == Some(s))
+            .ok_or(UnknownPunctuator)
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub struct UnknownPunctuator;
+
+impl Display for UnknownPunctuator {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("UnknownPunctuator")
+    }
+}
+
+impl Error for UnknownPunctuator {
+}
+
+#[cfg(test)]
+mod tests {
+    use rstest::rstest;
+    use strum::IntoEnumIterator;
+    use super::*;
+    use pretty_assertions::assert_eq;
+    use crate::{
+        Language,
+        Lexer,
+        LexerOptions,
+        PreprocessorTokenKind,
+    };
+
+    /// C++ has every punctuator of C.
+    fn cpp_options() -> LexerOptions {
+        LexerOptions {
+            language: Language::Cpp,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn round_trip() {
+        for punctuator in Punctuator::iter() {
+            let spelling = punctuator.as_str();
+            assert_eq!(spelling.parse(), Ok(punctuator));
+
+            let kinds: Vec<_> = Lexer::with_options(spelling, cpp_options()).map(|token| token.kind).collect();
+            assert_eq!(kinds, [PreprocessorTokenKind::Punctuator(punctuator)], "lexing {spelling:?}");
+            assert_eq!(kinds[0].to_string(), spelling);
+        }
+    }
+
+    #[test]
+    fn digraph_round_trip() {
+        for punctuator in Punctuator::iter() {
+            let Some(spelling) = punctuator.digraph() else {
+                continue;
+            };
+
+            assert_eq!(spelling.parse(), Ok(punctuator));
+
+            let kinds: Vec<_> = Lexer::with_options(spelling, cpp_options()).map(|token| token.kind).collect();
+            assert_eq!(kinds, [PreprocessorTokenKind::Digraph(punctuator)], "lexing {spelling:?}");
+            assert_eq!(kinds[0].to_string(), spelling);
+        }
+    }
+
+    #[test]
+    fn spellings_are_unique() {
+        let mut spellings: Vec<_> = Punctuator::iter()
+            .flat_map(|punctuator| [Some(punctuator.as_str()), punctuator.digraph()])
+            .flatten()
+            .collect();
+        spellings.sort();
+
+        let count = spellings.len();
+        spellings.dedup();
+        assert_eq!(spellings.len(), count);
+    }
+
+    #[rstest]
+    #[case(".*", &[Punctuator::FullStop, Punctuator::Asterisk])]
+    #[case("->*", &[Punctuator::PointerMemberAccessOperator, Punctuator::Asterisk])]
+    #[case("<=>", &[Punctuator::LessThanOrEqualTo, Punctuator::GreaterThan])]
+    fn cpp_only_in_c(#[case] input: &str, #[case] expected: &[Punctuator]) {
+        let punctuators: Vec<_> = Lexer::new(input).filter_map(|token| token.kind.punctuator()).collect();
+        assert_eq!(punctuators, expected);
+    }
+
+    #[rstest]
+    #[case("", Err(UnknownPunctuator))]
+    #[case("@", Err(UnknownPunctuator))]
+    #[case("<<<", Err(UnknownPunctuator))]
+    #[case("%:%:", Ok(Punctuator::DoublePound))]
+    #[case("]", Ok(Punctuator::RightSquareBracket))]
+    fn from_str(#[case] input: &str, #[case] expected: Result<Punctuator, UnknownPunctuator>) {
+        assert_eq!(input.parse(), expected);
+    }
+}